@@ -0,0 +1,6 @@
+Optimal - objective value 2711114.00000000
+      7 x_0_7                                           1                       0
+      9 x_0_9                                           1                       0
+     12 x_1_0                                           1                       0
+     31 b_1_0_9                                         1                       0
+     54 b_0_9_0                                         1                       0
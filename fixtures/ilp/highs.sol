@@ -0,0 +1,73 @@
+Model status
+Optimal
+
+# Primal solution values
+Feasible
+Objective 2711114
+# Columns 66
+x_0_0 0
+x_0_1 0
+x_0_2 0
+x_0_3 1e-10
+x_0_4 0
+x_0_5 0
+x_0_6 0
+x_0_7 0.9999999999
+x_0_8 0
+x_0_9 1
+x_0_10 0
+x_0_11 0
+x_1_0 1
+x_1_1 0
+x_1_2 0
+x_1_3 0
+x_1_4 0
+x_1_5 0
+x_1_6 0
+x_1_7 0
+x_1_8 0
+x_1_9 0
+x_1_10 0
+x_1_11 0
+b_0_0_3 0
+b_1_0_3 0
+b_0_0_4 0
+b_1_0_4 0
+b_0_0_6 0
+b_1_0_6 0
+b_0_0_9 0
+b_1_0_9 1
+b_0_1_2 0
+b_1_1_2 0
+b_0_2_1 0
+b_1_2_1 0
+b_0_2_8 0
+b_1_2_8 0
+b_0_3_0 0
+b_1_3_0 0
+b_0_3_5 0
+b_1_3_5 0
+b_0_4_0 0
+b_1_4_0 0
+b_0_4_6 0
+b_1_4_6 0
+b_0_4_9 0
+b_1_4_9 0
+b_0_5_3 0
+b_1_5_3 0
+b_0_5_6 0
+b_1_5_6 0
+b_0_5_9 0
+b_1_5_9 0
+b_0_9_0 1
+b_1_9_0 0
+b_0_9_4 0
+b_1_9_4 0
+b_0_9_5 0
+b_1_9_5 0
+b_0_9_10 0
+b_1_9_10 0
+b_0_10_6 0
+b_1_10_6 0
+b_0_10_9 0
+b_1_10_9 0
@@ -0,0 +1,83 @@
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::process;
+
+use paisleys_paradox::icfp::*;
+use paisleys_paradox::ilp::*;
+use paisleys_paradox::scorer::*;
+
+// Plenty for tiny problems, and already a big model for anything else
+const DEFAULT_POINTS: usize = 300;
+
+fn run(args: &[String]) -> Result<(), String> {
+    let usage = "Usage: ilp export <problem.json> <model.lp|model.mps> [--points N]\n       \
+                 ilp import <problem.json> <solver output> [--points N]";
+
+    let mut points = DEFAULT_POINTS;
+    let mut positional: Vec<&str> = Vec::new();
+    let mut rest = args.iter();
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "--points" => {
+                points = rest
+                    .next()
+                    .and_then(|n| n.parse().ok())
+                    .ok_or_else(|| format!("--points needs a number\n\n{}", usage))?
+            }
+            option if option.starts_with("--") => {
+                return Err(format!("Unknown option {}\n\n{}", option, usage))
+            }
+            other => positional.push(other),
+        }
+    }
+    let [command, problem_filename, filename] = positional[..] else {
+        return Err(usage.to_string());
+    };
+
+    let problem_file = fs::read_to_string(problem_filename)
+        .map_err(|e| format!("Can't read {}: {}", problem_filename, e))?;
+    let problem: Problem = serde_json::from_str(&problem_file)
+        .map_err(|e| format!("{} isn't a problem: {}", problem_filename, e))?;
+    let layout = Layout::new(&problem, points);
+
+    match command {
+        "export" => {
+            eprintln!("Building model over {} points", layout.points.len());
+            let model = formulate(&problem, &layout);
+            eprintln!(
+                "{} variables, {} constraints",
+                model.variables.len(),
+                model.constraints.len()
+            );
+            let text = if filename.ends_with(".mps") {
+                to_mps(&model)
+            } else {
+                to_lp(&model)
+            };
+            fs::write(filename, text).map_err(|e| format!("Can't write {}: {}", filename, e))?;
+            eprintln!("Saved {}", filename);
+        }
+        "import" => {
+            let text = fs::read_to_string(filename)
+                .map_err(|e| format!("Can't read {}: {}", filename, e))?;
+            let solution = read_solution(&problem, &layout, &text)?;
+            eprintln!("Scored {}", scorer(&problem, &solution));
+            let output = serde_json::to_string(&solution).map_err(|e| e.to_string())?;
+            io::stdout()
+                .write_all(output.as_bytes())
+                .map_err(|e| e.to_string())?;
+        }
+        other => return Err(format!("Unknown command {}\n\n{}", other, usage)),
+    }
+
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if let Err(error) = run(&args) {
+        eprintln!("ilp: {}", error);
+        process::exit(1);
+    }
+}
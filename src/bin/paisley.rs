@@ -0,0 +1,461 @@
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::process::{self, Command, Stdio};
+use std::thread;
+use std::time::Duration;
+
+use paisleys_paradox::icfp::*;
+use paisleys_paradox::pipeline;
+use paisleys_paradox::preview::*;
+use paisleys_paradox::repair::*;
+use paisleys_paradox::scorer::*;
+
+const SUBMISSION_URL: &str = "https://api.icfpcontest.com/submission";
+// The submission server doesn't like being rushed
+const SUBMISSION_DELAY: Duration = Duration::from_secs(3);
+const DEFAULT_COLUMNS: usize = 80;
+
+struct Subcommand {
+    name: &'static str,
+    usage: &'static str,
+    about: &'static str,
+    // Options that take a value, and switches that don't
+    options: &'static [&'static str],
+    switches: &'static [&'static str],
+    run: fn(&Args) -> Result<(), String>,
+}
+
+const SUBCOMMANDS: &[Subcommand] = &[
+    Subcommand {
+        name: "solve",
+        usage: "paisley solve <problem> [--pipeline greedy,tabu,assign] [--save]",
+        about: "Solve a problem and print the solution JSON. With --save it also \
+                goes in solutions/ under its score",
+        options: &["--pipeline"],
+        switches: &["--save"],
+        run: solve,
+    },
+    Subcommand {
+        name: "score",
+        usage: "paisley score <problem> <solution.json>",
+        about: "Print a solution's score",
+        options: &[],
+        switches: &[],
+        run: score,
+    },
+    Subcommand {
+        name: "validate",
+        usage: "paisley validate <problem> <solution.json>",
+        about: "Check a solution would be accepted, listing anything wrong with it",
+        options: &[],
+        switches: &[],
+        run: validate,
+    },
+    Subcommand {
+        name: "render",
+        usage: "paisley render <problem> <solution.json> [--columns N]",
+        about: "Draw a solution in the terminal",
+        options: &["--columns"],
+        switches: &[],
+        run: render_solution,
+    },
+    Subcommand {
+        name: "batch",
+        usage: "paisley batch <pipeline> <problem>...",
+        about: "Solve several problems one after another, saving every solution \
+                in solutions/",
+        options: &[],
+        switches: &[],
+        run: batch,
+    },
+    Subcommand {
+        name: "best",
+        usage: "paisley best [<problem id>...]",
+        about: "List the best solution in solutions/ for each problem (or just \
+                the ones given), scoring any that don't have a score yet",
+        options: &[],
+        switches: &[],
+        run: best,
+    },
+    Subcommand {
+        name: "submit",
+        usage: "paisley submit <solution.json>...",
+        about: "Submit solutions, using the API_KEY environment variable. The \
+                problem comes from each file's name",
+        options: &[],
+        switches: &[],
+        run: submit,
+    },
+];
+
+// A subcommand's arguments, split into positional ones, options with their
+// values, and switches
+struct Args<'a> {
+    usage: &'static str,
+    positional: Vec<&'a str>,
+    options: HashMap<&'a str, &'a str>,
+    switches: Vec<&'a str>,
+}
+
+impl<'a> Args<'a> {
+    fn parse(subcommand: &Subcommand, args: &'a [String]) -> Result<Args<'a>, String> {
+        let mut parsed = Args {
+            usage: subcommand.usage,
+            positional: Vec::new(),
+            options: HashMap::new(),
+            switches: Vec::new(),
+        };
+        let mut rest = args.iter();
+        while let Some(arg) = rest.next() {
+            let arg = arg.as_str();
+            if subcommand.options.contains(&arg) {
+                let value = rest
+                    .next()
+                    .ok_or_else(|| format!("{} needs a value", arg))?;
+                parsed.options.insert(arg, value.as_str());
+            } else if subcommand.switches.contains(&arg) {
+                parsed.switches.push(arg);
+            } else if arg.starts_with("--") {
+                return Err(format!("Unknown option {}", arg));
+            } else {
+                parsed.positional.push(arg);
+            }
+        }
+        Ok(parsed)
+    }
+
+    // Exactly `count` positional arguments
+    fn exactly(&self, count: usize) -> Result<&[&'a str], String> {
+        if self.positional.len() == count {
+            Ok(&self.positional)
+        } else {
+            Err(format!(
+                "Expected {} arguments but got {}\n\nUsage: {}",
+                count,
+                self.positional.len(),
+                self.usage
+            ))
+        }
+    }
+}
+
+// Problems can be given by number or by file
+fn problem_path(problem: &str) -> String {
+    if problem.ends_with(".json") {
+        problem.to_string()
+    } else {
+        format!("problems/problem-{}.json", problem)
+    }
+}
+
+fn problem_id(path: &str) -> Result<String, String> {
+    Path::new(path)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .and_then(|stem| stem.strip_prefix("problem-"))
+        .map(|id| id.to_string())
+        .ok_or_else(|| format!("Can't tell which problem {} is", path))
+}
+
+fn read(path: &str) -> Result<String, String> {
+    fs::read_to_string(path).map_err(|e| format!("Can't read {}: {}", path, e))
+}
+
+fn read_problem(problem: &str) -> Result<Problem, String> {
+    let path = problem_path(problem);
+    serde_json::from_str(&read(&path)?).map_err(|e| format!("{} isn't a problem: {}", path, e))
+}
+
+fn read_solution(path: &str) -> Result<Solution, String> {
+    serde_json::from_str(&read(path)?).map_err(|e| format!("{} isn't a solution: {}", path, e))
+}
+
+// Save alongside everything else in solutions/, with the score and how we
+// got it in the name
+fn save(id: &str, score: f32, strategy: &str, solution: &Solution) -> Result<String, String> {
+    let filename = format!(
+        "solutions/solution-{}-score-{}-strategy-{}.json",
+        id,
+        score,
+        strategy.replace(',', "+")
+    );
+    let output = serde_json::to_string(solution).map_err(|e| e.to_string())?;
+    fs::write(&filename, output).map_err(|e| format!("Can't write {}: {}", filename, e))?;
+    Ok(filename)
+}
+
+fn solve(args: &Args) -> Result<(), String> {
+    let given = args.exactly(1)?[0];
+    let names: Vec<&str> = args
+        .options
+        .get("--pipeline")
+        .map_or("trivial", |p| *p)
+        .split(',')
+        .collect();
+    let problem = read_problem(given)?;
+    let solution = pipeline::solve(&problem, &names)?;
+
+    if args.switches.contains(&"--save") {
+        let id = problem_id(&problem_path(given))?;
+        let score = scorer(&problem, &solution);
+        eprintln!("Saved {}", save(&id, score, &names.join(","), &solution)?);
+    }
+    println!(
+        "{}",
+        serde_json::to_string(&solution).map_err(|e| e.to_string())?
+    );
+    Ok(())
+}
+
+fn score(args: &Args) -> Result<(), String> {
+    let files = args.exactly(2)?;
+    let (problem, path) = (files[0], files[1]);
+    let (problem, solution) = (read_problem(problem)?, read_solution(path)?);
+    // The scorer quietly gives nothing for a solution missing anyone, so
+    // that's an error; anything else only gets it rejected on submission
+    for violation in violations(&problem, &solution) {
+        eprintln!("Warning: {}", violation);
+    }
+    if solution.placements.len() != problem.musicians.len() {
+        return Err(format!(
+            "{} places {} musicians but there are {}",
+            path,
+            solution.placements.len(),
+            problem.musicians.len()
+        ));
+    }
+    println!("{}", scorer(&problem, &solution));
+    Ok(())
+}
+
+fn validate(args: &Args) -> Result<(), String> {
+    let files = args.exactly(2)?;
+    let (problem, solution) = (files[0], files[1]);
+    let found = violations(&read_problem(problem)?, &read_solution(solution)?);
+    if found.is_empty() {
+        println!("{} is valid", solution);
+        return Ok(());
+    }
+    for violation in &found {
+        println!("{}", violation);
+    }
+    Err(format!("{} has {} problems", solution, found.len()))
+}
+
+fn render_solution(args: &Args) -> Result<(), String> {
+    let files = args.exactly(2)?;
+    let (problem, solution) = (files[0], files[1]);
+    let columns = match args.options.get("--columns") {
+        Some(columns) => columns
+            .parse::<usize>()
+            .map_err(|_| format!("--columns needs a number, not {}", columns))?,
+        None => env::var("COLUMNS")
+            .ok()
+            .and_then(|c| c.parse().ok())
+            .unwrap_or(DEFAULT_COLUMNS),
+    };
+    let problem = read_problem(problem)?;
+    let solution = read_solution(solution)?;
+    for line in render(&problem, &solution, columns.max(10)) {
+        println!("{}", line.trim_end());
+    }
+    println!("score {}", scorer(&problem, &solution));
+    Ok(())
+}
+
+fn batch(args: &Args) -> Result<(), String> {
+    let Some((names, problems)) = args.positional.split_first() else {
+        return Err(format!("Need a pipeline\n\nUsage: {}", args.usage));
+    };
+    if problems.is_empty() {
+        return Err(format!(
+            "Need at least one problem\n\nUsage: {}",
+            args.usage
+        ));
+    }
+    let names: Vec<&str> = names.split(',').collect();
+    // Check the pipeline before spending time on anything
+    pipeline::parse(names.strip_prefix(&["split"]).unwrap_or(&names))?;
+
+    for problem in problems {
+        let path = problem_path(problem);
+        let id = problem_id(&path)?;
+        eprintln!("Solving problem {}", id);
+        let problem = read_problem(problem)?;
+        let solution = pipeline::solve(&problem, &names)?;
+        let score = scorer(&problem, &solution);
+        let filename = save(&id, score, &names.join(","), &solution)?;
+        println!("{}\t{}\t{}", id, score, filename);
+    }
+    Ok(())
+}
+
+// Problem id, score and strategy from a name like
+// solution-42-score-123-strategy-greedy.json. The score might be "unknown"
+fn parse_solution_name(name: &str) -> Option<(&str, &str, &str)> {
+    let rest = name.strip_prefix("solution-")?.strip_suffix(".json")?;
+    let (id, rest) = rest.split_once("-score-")?;
+    let (score, strategy) = rest.split_once("-strategy-")?;
+    Some((id, score, strategy))
+}
+
+fn best(args: &Args) -> Result<(), String> {
+    let entries = fs::read_dir("solutions").map_err(|e| format!("Can't read solutions/: {}", e))?;
+    let mut names: Vec<String> = entries
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .collect();
+    names.sort();
+
+    let mut problems: HashMap<String, Problem> = HashMap::new();
+    let mut best: BTreeMap<u32, (f32, String)> = BTreeMap::new();
+    for name in &names {
+        let Some((id, score, _)) = parse_solution_name(name) else {
+            continue;
+        };
+        if !args.positional.is_empty() && !args.positional.contains(&id) {
+            continue;
+        }
+        let Ok(number) = id.parse::<u32>() else {
+            continue;
+        };
+        let path = format!("solutions/{}", name);
+        let score = match score.parse::<f32>() {
+            Ok(score) => score,
+            Err(_) => {
+                if !problems.contains_key(id) {
+                    problems.insert(id.to_string(), read_problem(id)?);
+                }
+                eprintln!("Scoring {}", path);
+                scorer(&problems[id], &read_solution(&path)?)
+            }
+        };
+        let better = match best.get(&number) {
+            Some((best, _)) => score > *best,
+            None => true,
+        };
+        if better {
+            best.insert(number, (score, path));
+        }
+    }
+
+    for (id, (score, path)) in best {
+        println!("{}\t{}\t{}", id, score, path);
+    }
+    Ok(())
+}
+
+fn submit(args: &Args) -> Result<(), String> {
+    if args.positional.is_empty() {
+        return Err(format!("Nothing to submit\n\nUsage: {}", args.usage));
+    }
+    let key = env::var("API_KEY").unwrap_or_default();
+    if key.is_empty() {
+        return Err("Set API_KEY to submit".to_string());
+    }
+
+    for (i, path) in args.positional.iter().enumerate() {
+        let name = Path::new(path)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or(path);
+        let (id, _, _) = parse_solution_name(name)
+            .ok_or_else(|| format!("Can't tell which problem {} is for", path))?;
+        let id: u32 = id
+            .parse()
+            .map_err(|_| format!("Can't tell which problem {} is for", path))?;
+        // Make sure it's a solution before sending it anywhere
+        read_solution(path)?;
+        let payload = serde_json::json!({
+            "problem_id": id,
+            "contents": read(path)?.replace('\n', ""),
+        });
+
+        if i > 0 {
+            thread::sleep(SUBMISSION_DELAY);
+        }
+        eprintln!("Submitting {} for problem {}", path, id);
+        let mut curl = Command::new("curl")
+            .args(["--silent", "--show-error", "--request", "POST"])
+            .args(["--header", &format!("Authorization: Bearer {}", key)])
+            .args(["--header", "Content-Type: application/json"])
+            .args(["--data-binary", "@-", SUBMISSION_URL])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Can't run curl: {}", e))?;
+        curl.stdin
+            .take()
+            .unwrap()
+            .write_all(payload.to_string().as_bytes())
+            .map_err(|e| format!("Can't send to curl: {}", e))?;
+        let output = curl
+            .wait_with_output()
+            .map_err(|e| format!("curl failed: {}", e))?;
+        if !output.status.success() {
+            return Err(format!("Submitting {} failed", path));
+        }
+        println!(
+            "{}\t{}",
+            path,
+            String::from_utf8_lossy(&output.stdout).trim()
+        );
+    }
+    Ok(())
+}
+
+fn overview() -> String {
+    let mut text = String::from("Usage: paisley <command> [arguments]\n\nCommands:\n");
+    for subcommand in SUBCOMMANDS {
+        text.push_str(&format!("  {}\n", subcommand.usage));
+    }
+    text.push_str("\nRun paisley help <command> for more about one of them");
+    text
+}
+
+fn help(subcommand: &Subcommand) -> String {
+    format!("Usage: {}\n\n{}", subcommand.usage, subcommand.about)
+}
+
+fn find(name: &str) -> Result<&'static Subcommand, String> {
+    SUBCOMMANDS
+        .iter()
+        .find(|subcommand| subcommand.name == name)
+        .ok_or_else(|| format!("Unknown command {}\n\n{}", name, overview()))
+}
+
+fn run(args: &[String]) -> Result<(), String> {
+    let Some((name, rest)) = args.split_first() else {
+        println!("{}", overview());
+        return Ok(());
+    };
+    match name.as_str() {
+        "help" | "--help" | "-h" => {
+            match rest.first() {
+                Some(name) => println!("{}", help(find(name)?)),
+                None => println!("{}", overview()),
+            }
+            Ok(())
+        }
+        name => {
+            let subcommand = find(name)?;
+            if rest.iter().any(|arg| arg == "--help" || arg == "-h") {
+                println!("{}", help(subcommand));
+                return Ok(());
+            }
+            let args = Args::parse(subcommand, rest)
+                .map_err(|e| format!("{}\n\nUsage: {}", e, subcommand.usage))?;
+            (subcommand.run)(&args)
+        }
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if let Err(error) = run(&args) {
+        eprintln!("paisley: {}", error);
+        process::exit(1);
+    }
+}
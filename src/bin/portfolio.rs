@@ -0,0 +1,281 @@
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::Path;
+use std::process::{self, Child, Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use paisleys_paradox::icfp::*;
+use paisleys_paradox::scorer::*;
+
+// Everyone gets this long, all running at once
+const TIME_BUDGET: Duration = Duration::from_secs(300);
+// Solvers are told to wrap up this long before the budget runs out, leaving
+// time to repair and write out what they have
+const WRAP_UP: Duration = Duration::from_secs(20);
+// At the checkpoint, only the solvers doing best so far get to carry on
+const CHECKPOINT: Duration = Duration::from_secs(100);
+const SURVIVORS: usize = 2;
+const DEFAULT_SOLVERS: [&str; 4] = ["greedy,blockers", "lattice,assign", "tabu", "lns"];
+// How every solver did on every problem, one line each, across runs
+const RESULTS: &str = "solutions/portfolio.tsv";
+
+// One solver pipeline running as its own `solve` process
+struct Entrant {
+    name: String,
+    child: Child,
+    // The best score the solver has reported on stderr so far
+    reported: Arc<Mutex<Option<f64>>>,
+    output: thread::JoinHandle<String>,
+    killed: bool,
+    // How long after the start it finished or was stopped
+    took: Option<Duration>,
+}
+
+// Solvers log progress in various ways, but every complete solution they
+// find is also reported as a "score N" line
+fn reported_score(line: &str) -> Option<f64> {
+    line.strip_prefix("score ")?.trim().parse().ok()
+}
+
+fn launch(solve: &Path, name: &str, problem_json: &str) -> Result<Entrant, String> {
+    let mut child = Command::new(solve)
+        .arg("--time-limit")
+        .arg((TIME_BUDGET - WRAP_UP).as_secs().to_string())
+        .arg(name)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Can't start {}: {}", solve.display(), e))?;
+
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(problem_json.as_bytes())
+        .map_err(|e| format!("Can't send the problem to {}: {}", name, e))?;
+
+    let reported = Arc::new(Mutex::new(None));
+    let stderr = child.stderr.take().unwrap();
+    let progress = reported.clone();
+    thread::spawn(move || {
+        for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+            if let Some(score) = reported_score(&line) {
+                let mut best = progress.lock().unwrap();
+                *best = Some(best.map_or(score, |best: f64| best.max(score)));
+            }
+        }
+    });
+
+    let mut stdout = child.stdout.take().unwrap();
+    let output = thread::spawn(move || {
+        let mut output = String::new();
+        // A killed solver just leaves us with nothing
+        let _ = stdout.read_to_string(&mut output);
+        output
+    });
+
+    Ok(Entrant {
+        name: name.to_string(),
+        child,
+        reported,
+        output,
+        killed: false,
+        took: None,
+    })
+}
+
+fn stop(entrant: &mut Entrant, start: Instant) -> Result<(), String> {
+    entrant
+        .child
+        .kill()
+        .map_err(|e| format!("Can't stop {}: {}", entrant.name, e))?;
+    entrant.killed = true;
+    entrant.took = Some(start.elapsed());
+    Ok(())
+}
+
+fn still_running(entrant: &mut Entrant, start: Instant) -> Result<bool, String> {
+    match entrant.child.try_wait() {
+        Ok(Some(_)) => {
+            entrant.took.get_or_insert_with(|| start.elapsed());
+            Ok(false)
+        }
+        Ok(None) => Ok(true),
+        Err(e) => Err(format!("Lost track of {}: {}", entrant.name, e)),
+    }
+}
+
+// Add this run's results to the rest, starting the file if there isn't one
+fn record(results: &[String]) -> Result<(), String> {
+    let fresh = !Path::new(RESULTS).exists();
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(RESULTS)
+        .map_err(|e| format!("Can't open {}: {}", RESULTS, e))?;
+    let mut lines = String::new();
+    if fresh {
+        lines.push_str("problem\tsolver\toutcome\tscore\tseconds\n");
+    }
+    for result in results {
+        lines.push_str(result);
+        lines.push('\n');
+    }
+    file.write_all(lines.as_bytes())
+        .map_err(|e| format!("Can't write {}: {}", RESULTS, e))
+}
+
+fn run(args: &[String]) -> Result<(), String> {
+    let usage = "Usage: portfolio problems/problem-N.json [solver,post-pass ...]";
+    let problem_filename = args.first().ok_or(usage)?;
+    let solvers: Vec<&str> = if args.len() > 1 {
+        args[1..].iter().map(|s| s.as_str()).collect()
+    } else {
+        DEFAULT_SOLVERS.to_vec()
+    };
+
+    let problem_json = fs::read_to_string(problem_filename)
+        .map_err(|e| format!("Can't read {}: {}", problem_filename, e))?;
+    let problem: Problem = serde_json::from_str(&problem_json)
+        .map_err(|e| format!("{} isn't a problem: {}", problem_filename, e))?;
+    let problem_id = Path::new(problem_filename)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .and_then(|stem| stem.strip_prefix("problem-"))
+        .ok_or("Problem file should be named problem-N.json")?
+        .to_string();
+
+    // The solve binary lives next to this one
+    let solve = env::current_exe()
+        .map_err(|e| e.to_string())?
+        .with_file_name("solve");
+
+    let start = Instant::now();
+    let mut entrants: Vec<Entrant> = Vec::new();
+    for name in &solvers {
+        eprintln!("Starting {}", name);
+        entrants.push(launch(&solve, name, &problem_json)?);
+    }
+
+    let mut raced = false;
+    loop {
+        let mut running = 0;
+        for entrant in entrants.iter_mut() {
+            if still_running(entrant, start)? {
+                running += 1;
+            }
+        }
+        if running == 0 {
+            break;
+        }
+        if start.elapsed() >= TIME_BUDGET {
+            for entrant in entrants.iter_mut() {
+                if still_running(entrant, start)? {
+                    eprintln!("Out of time, stopping {}", entrant.name);
+                    stop(entrant, start)?;
+                }
+            }
+            break;
+        }
+
+        if !raced && start.elapsed() >= CHECKPOINT {
+            raced = true;
+            let mut standings: Vec<(f64, usize)> = entrants
+                .iter()
+                .enumerate()
+                .map(|(i, e)| (e.reported.lock().unwrap().unwrap_or(f64::MIN), i))
+                .collect();
+            standings.sort_by(|a, b| b.0.total_cmp(&a.0));
+            for &(score, i) in standings.iter().skip(SURVIVORS) {
+                let entrant = &mut entrants[i];
+                if still_running(entrant, start)? {
+                    eprintln!("{} is behind at {}, stopping it", entrant.name, score);
+                    stop(entrant, start)?;
+                }
+            }
+        }
+
+        thread::sleep(Duration::from_millis(100));
+    }
+
+    let mut best: Option<(f32, String, Solution)> = None;
+    let mut results = Vec::new();
+    for mut entrant in entrants {
+        entrant
+            .child
+            .wait()
+            .map_err(|e| format!("Lost track of {}: {}", entrant.name, e))?;
+        let output = entrant.output.join().unwrap();
+        let seconds = entrant
+            .took
+            .unwrap_or_else(|| start.elapsed())
+            .as_secs_f32();
+        let result = |outcome: &str, score: Option<f64>| {
+            let score = score.map_or("-".to_string(), |score| score.to_string());
+            format!(
+                "{}\t{}\t{}\t{}\t{:.1}",
+                problem_id, entrant.name, outcome, score, seconds
+            )
+        };
+        if entrant.killed {
+            // Stopped solvers still get credit for how far they got
+            let reported = *entrant.reported.lock().unwrap();
+            results.push(result("stopped", reported));
+            continue;
+        }
+        let solution: Solution = match serde_json::from_str(&output) {
+            Ok(solution) => solution,
+            Err(_) => {
+                eprintln!("{} didn't produce a solution", entrant.name);
+                results.push(result("failed", None));
+                continue;
+            }
+        };
+        let score = scorer(&problem, &solution);
+        eprintln!("{} scored {}", entrant.name, score);
+        results.push(result("finished", Some(score as f64)));
+        let better = match &best {
+            Some((best, _, _)) => score > *best,
+            None => true,
+        };
+        if better {
+            best = Some((score, entrant.name, solution));
+        }
+    }
+
+    record(&results)?;
+
+    let (score, winner, solution) = best.ok_or(format!(
+        "No solver finished within {}s",
+        TIME_BUDGET.as_secs()
+    ))?;
+    eprintln!("{} won with {}", winner, score);
+
+    // Record the winner in the solution's file name, alongside everything
+    // else in solutions/
+    let output = serde_json::to_string(&solution).map_err(|e| e.to_string())?;
+    let filename = format!(
+        "solutions/solution-{}-score-{}-strategy-portfolio-{}.json",
+        problem_id,
+        score,
+        winner.replace(',', "+")
+    );
+    fs::write(&filename, &output).map_err(|e| format!("Can't write {}: {}", filename, e))?;
+    eprintln!("Saved {}", filename);
+
+    io::stdout()
+        .write_all(output.as_bytes())
+        .map_err(|e| e.to_string())
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if let Err(error) = run(&args) {
+        eprintln!("portfolio: {}", error);
+        process::exit(1);
+    }
+}
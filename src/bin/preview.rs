@@ -0,0 +1,55 @@
+use std::env;
+use std::fs;
+use std::process;
+
+use paisleys_paradox::icfp::*;
+use paisleys_paradox::preview::*;
+use paisleys_paradox::scorer::*;
+
+fn run(args: &[String]) -> Result<(), String> {
+    let usage = "Usage: preview problems/problem-N.json solution.json";
+    let (problem_filename, solution_filename) = match args {
+        [problem, solution, ..] => (problem, solution),
+        _ => return Err(usage.to_string()),
+    };
+
+    eprintln!("Loading problem file {}", problem_filename);
+    let problem_file = fs::read_to_string(problem_filename)
+        .map_err(|e| format!("Can't read {}: {}", problem_filename, e))?;
+    let problem: Problem = serde_json::from_str(&problem_file)
+        .map_err(|e| format!("{} isn't a problem: {}", problem_filename, e))?;
+
+    eprintln!("Loading solution file {}", solution_filename);
+    let solution_file = fs::read_to_string(solution_filename)
+        .map_err(|e| format!("Can't read {}: {}", solution_filename, e))?;
+    let solution: Solution = serde_json::from_str(&solution_file)
+        .map_err(|e| format!("{} isn't a solution: {}", solution_filename, e))?;
+
+    // bin/preview exports COLUMNS from the terminal
+    let cols = env::var("COLUMNS")
+        .ok()
+        .and_then(|c| c.parse::<usize>().ok())
+        .unwrap_or(80)
+        .max(10);
+
+    for line in render(&problem, &solution, cols) {
+        println!("{}", line.trim_end());
+    }
+    println!(
+        "score {}  musicians {}  attendees {}  pillars {}",
+        scorer(&problem, &solution),
+        solution.placements.len(),
+        problem.attendees.len(),
+        problem.pillars.len()
+    );
+
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if let Err(error) = run(&args) {
+        eprintln!("preview: {}", error);
+        process::exit(1);
+    }
+}
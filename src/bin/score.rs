@@ -1,32 +1,49 @@
 use std::env;
 use std::fs;
-use std::io::{self};
+use std::process;
 
 use paisleys_paradox::icfp::*;
+use paisleys_paradox::repair::*;
 use paisleys_paradox::scorer::*;
 
-fn main() -> io::Result<()> {
-    let args: Vec<String> = env::args().collect();
+fn run(args: &[String]) -> Result<(), String> {
+    let [problem_filename, solution_filename] = args else {
+        return Err("Usage: score <problem.json> <solution.json>".to_string());
+    };
 
-    // if args.len() < 3 {
-    //     eprintln!("Please provide a problem.json and solution.json");
-    //     exit;
-    // }
-
-    let problem_filename = &args[1];
     eprintln!("Loading problem file {}", problem_filename);
-    let problem_file = fs::read_to_string(problem_filename).expect("Error reading problem file");
-    let problem: Problem =
-        serde_json::from_str(&problem_file).expect("Failed to parse problem JSON");
+    let problem_file = fs::read_to_string(problem_filename)
+        .map_err(|e| format!("Can't read {}: {}", problem_filename, e))?;
+    let problem: Problem = serde_json::from_str(&problem_file)
+        .map_err(|e| format!("{} isn't a problem: {}", problem_filename, e))?;
 
-    let solution_filename = &args[2];
     eprintln!("Loading solution file {}", solution_filename);
-    let solution_file = fs::read_to_string(solution_filename).expect("Error reading solution file");
-    let solution: Solution =
-        serde_json::from_str(&solution_file).expect("Failed to parse solution JSON");
+    let solution_file = fs::read_to_string(solution_filename)
+        .map_err(|e| format!("Can't read {}: {}", solution_filename, e))?;
+    let solution: Solution = serde_json::from_str(&solution_file)
+        .map_err(|e| format!("{} isn't a solution: {}", solution_filename, e))?;
+
+    for violation in violations(&problem, &solution) {
+        eprintln!("Warning: {}", violation);
+    }
+    if solution.placements.len() != problem.musicians.len() {
+        return Err(format!(
+            "{} places {} musicians but there are {}",
+            solution_filename,
+            solution.placements.len(),
+            problem.musicians.len()
+        ));
+    }
 
     eprintln!("Calculating score");
     println!("{}", scorer(&problem, &solution));
-
     Ok(())
 }
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if let Err(error) = run(&args) {
+        eprintln!("score: {}", error);
+        process::exit(1);
+    }
+}
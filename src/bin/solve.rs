@@ -1,37 +1,48 @@
 use std::env;
 use std::io::{self, Read, Write};
+use std::process;
+use std::time::Duration;
 
+use paisleys_paradox::deadline;
 use paisleys_paradox::icfp::*;
 // use paisleys_paradox::scorer::*;
 
-fn main() -> io::Result<()> {
+fn run(args: &[String]) -> Result<(), String> {
+    // "--time-limit N" gives the whole run N seconds
+    let mut args = args;
+    if let Some(limit) = args.first().filter(|arg| *arg == "--time-limit") {
+        let seconds: u64 = args
+            .get(1)
+            .and_then(|seconds| seconds.parse().ok())
+            .ok_or(format!("{} needs a number of seconds", limit))?;
+        deadline::set(Duration::from_secs(seconds));
+        args = &args[2..];
+    }
+
     let mut buffer = String::new();
-    io::stdin().read_to_string(&mut buffer)?;
+    io::stdin()
+        .read_to_string(&mut buffer)
+        .map_err(|e| format!("Can't read the problem: {}", e))?;
 
-    let problem: Problem = serde_json::from_str(&buffer).expect("Failed to parse JSON");
+    let problem: Problem =
+        serde_json::from_str(&buffer).map_err(|e| format!("That isn't a problem: {}", e))?;
 
-    let args: Vec<String> = env::args().collect();
-    let solver = args.get(1);
-    let solver = match solver {
-        Some(name) => name.as_str(),
-        None => "trivial",
-    };
+    // Stages can be given as "greedy,tabu,assign" or "greedy tabu assign":
+    // a solver, then any post-passes to run over its result
+    let mut pipeline: Vec<&str> = args.iter().flat_map(|arg| arg.split(',')).collect();
+    if pipeline.is_empty() {
+        pipeline.push("trivial");
+    }
 
-    eprintln!("Solving problem with {}", solver);
+    eprintln!("Solving problem with {}", pipeline.join(","));
 
-    let solution = match solver {
-        "trivial" => paisleys_paradox::solver::trivial::solve(&problem),
-        "random" => paisleys_paradox::solver::random::solve(&problem),
-        "random-best" => paisleys_paradox::solver::random_of_n::solve(&problem),
-        "gravity-of-n" => paisleys_paradox::solver::gravity_of_n::solve(&problem),
-        "hot-cold" => paisleys_paradox::solver::hot_cold::solve(&problem),
-        "ga" => paisleys_paradox::solver::ga::solve(&problem),
-        _ => panic!("Invalid solver"),
-    };
+    let solution = paisleys_paradox::pipeline::solve(&problem, &pipeline)?;
 
-    let output = serde_json::to_string(&solution).expect("Failed to generate JSON");
+    let output = serde_json::to_string(&solution).map_err(|e| e.to_string())?;
 
-    io::stdout().write_all(output.as_bytes())?;
+    io::stdout()
+        .write_all(output.as_bytes())
+        .map_err(|e| e.to_string())?;
 
     // eprintln!("");
     // eprintln!("Score: {}", scorer(problem, solution));
@@ -39,3 +50,11 @@ fn main() -> io::Result<()> {
 
     Ok(())
 }
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if let Err(error) = run(&args) {
+        eprintln!("solve: {}", error);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,187 @@
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::fs;
+use std::process;
+
+use paisleys_paradox::icfp::*;
+use paisleys_paradox::params::*;
+use paisleys_paradox::scorer::*;
+use rand::seq::SliceRandom;
+
+const DEFAULT_SAMPLES: usize = 16;
+
+// Problems that probably want similar settings: by how many musicians there
+// are, and whether there are pillars in the way
+fn class(problem: &Problem) -> String {
+    let size = match problem.musicians.len() {
+        0..=50 => "small",
+        51..=300 => "medium",
+        _ => "large",
+    };
+    if problem.pillars.is_empty() {
+        size.to_string()
+    } else {
+        format!("{}-pillars", size)
+    }
+}
+
+struct Tuning<'a> {
+    solver: TunableSolver,
+    configs: Vec<Params>,
+    problems: Vec<&'a Problem>,
+    // Score of each (config, problem) pair run so far
+    scores: HashMap<(usize, usize), f64>,
+}
+
+impl<'a> Tuning<'a> {
+    fn score(&mut self, config: usize, problem: usize) -> f64 {
+        if let Some(score) = self.scores.get(&(config, problem)) {
+            return *score;
+        }
+        eprintln!("Trying {}", self.configs[config]);
+        let solution = (self.solver)(self.problems[problem], &self.configs[config]);
+        let score = scorer(self.problems[problem], &solution) as f64;
+        self.scores.insert((config, problem), score);
+        score
+    }
+
+    // Configs from best to worst, by their average score on `problems`.
+    // Scores are relative to the best any of `configs` got on each problem,
+    // so big problems don't drown out small ones
+    fn rank(&mut self, configs: &[usize], problems: &[usize]) -> Vec<(usize, f64)> {
+        let mut totals = vec![0.0; configs.len()];
+        for &p in problems {
+            let scores: Vec<f64> = configs.iter().map(|&c| self.score(c, p)).collect();
+            let scale = scores.iter().fold(1.0_f64, |scale, s| scale.max(s.abs()));
+            for (total, score) in totals.iter_mut().zip(scores) {
+                *total += score / scale;
+            }
+        }
+        let mut ranked: Vec<(usize, f64)> = configs
+            .iter()
+            .zip(totals)
+            .map(|(&c, total)| (c, total / problems.len() as f64))
+            .collect();
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+        ranked
+    }
+
+    // Everything on everything
+    fn random_search(&mut self) -> (usize, f64) {
+        let configs: Vec<usize> = (0..self.configs.len()).collect();
+        let problems: Vec<usize> = (0..self.problems.len()).collect();
+        self.rank(&configs, &problems)[0]
+    }
+
+    // Try every config on one problem, keep the better half and give them
+    // twice as many problems, and so on until one config is left
+    fn successive_halving(&mut self) -> (usize, f64) {
+        let mut problems: Vec<usize> = (0..self.problems.len()).collect();
+        problems.shuffle(&mut rand::thread_rng());
+        let mut configs: Vec<usize> = (0..self.configs.len()).collect();
+        let mut budget = 1;
+        loop {
+            let subset = &problems[..budget.min(problems.len())];
+            let ranked = self.rank(&configs, subset);
+            if ranked.len() == 1 {
+                return ranked[0];
+            }
+            eprintln!(
+                "{} configs on {} problems, best so far {} ({:.3})",
+                ranked.len(),
+                subset.len(),
+                self.configs[ranked[0].0],
+                ranked[0].1
+            );
+            configs = ranked
+                .iter()
+                .take(ranked.len() - ranked.len() / 2)
+                .map(|(c, _)| *c)
+                .collect();
+            budget *= 2;
+        }
+    }
+}
+
+fn run(args: &[String]) -> Result<(), String> {
+    let usage = "Usage: tune <solver> [--random] [--samples N] <problem id>...";
+    let solver_name = args.first().ok_or(usage)?;
+    let (schema, solver) = tunable(solver_name)
+        .ok_or_else(|| format!("{} has no tunable parameters\n\n{}", solver_name, usage))?;
+
+    let mut random = false;
+    let mut samples = DEFAULT_SAMPLES;
+    let mut ids: Vec<&str> = Vec::new();
+    let mut rest = args.iter().skip(1);
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "--random" => random = true,
+            "--samples" => {
+                samples = rest
+                    .next()
+                    .and_then(|n| n.parse().ok())
+                    .ok_or_else(|| format!("--samples needs a number\n\n{}", usage))?
+            }
+            option if option.starts_with("--") => {
+                return Err(format!("Unknown option {}\n\n{}", option, usage))
+            }
+            id => ids.push(id),
+        }
+    }
+    if ids.is_empty() {
+        return Err(format!("Need at least one problem\n\n{}", usage));
+    }
+
+    let problems: Vec<Problem> = ids
+        .iter()
+        .map(|id| {
+            let filename = format!("problems/problem-{}.json", id);
+            let buffer = fs::read_to_string(&filename)
+                .map_err(|e| format!("Can't read {}: {}", filename, e))?;
+            serde_json::from_str(&buffer)
+                .map_err(|e| format!("{} isn't a problem: {}", filename, e))
+        })
+        .collect::<Result<_, String>>()?;
+
+    let mut classes: BTreeMap<String, Vec<&Problem>> = BTreeMap::new();
+    for problem in &problems {
+        classes.entry(class(problem)).or_default().push(problem);
+    }
+
+    // The defaults always get a look in
+    let mut rng = rand::thread_rng();
+    let mut configs = vec![Params::defaults(schema)];
+    while configs.len() < samples.max(1) {
+        configs.push(Params::sample(schema, &mut rng));
+    }
+
+    for (class, problems) in classes {
+        eprintln!(
+            "Tuning {} on {} {} problems",
+            solver_name,
+            problems.len(),
+            class
+        );
+        let mut tuning = Tuning {
+            solver,
+            configs: configs.clone(),
+            problems,
+            scores: HashMap::new(),
+        };
+        let (best, relative) = if random {
+            tuning.random_search()
+        } else {
+            tuning.successive_halving()
+        };
+        println!("{}\t{}\t{:.3}", class, tuning.configs[best], relative);
+    }
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if let Err(error) = run(&args) {
+        eprintln!("tune: {}", error);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,291 @@
+use crate::icfp::*;
+use std::collections::HashSet;
+
+// Musicians need this much room from each other and the edge of the stage
+pub const MUSICIAN_SPACING: f32 = 10.0;
+// Musicians block sound within this radius
+pub const MUSICIAN_RADIUS: f32 = 5.0;
+// How many candidate points solvers look at, unless they have reason to
+// look at fewer
+pub const MAX_CANDIDATES: usize = 2000;
+// Edge rows are twice as dense as musicians can actually stand, so solvers
+// get some choice about where along the edge each one goes
+const EDGE_SPACING: f32 = 5.0;
+// How much further apart lattice rows are than a perfect packing, so points
+// in neighbouring rows are still far enough apart after rounding
+const ROW_SLACK: f64 = 0.01;
+
+// The area musicians can stand in: x_min, y_min, x_max, y_max
+pub fn usable_area(problem: &Problem) -> (f32, f32, f32, f32) {
+    (
+        problem.stage_bottom_left[0] + MUSICIAN_SPACING,
+        problem.stage_bottom_left[1] + MUSICIAN_SPACING,
+        problem.stage_bottom_left[0] + problem.stage_width - MUSICIAN_SPACING,
+        problem.stage_bottom_left[1] + problem.stage_height - MUSICIAN_SPACING,
+    )
+}
+
+// from, from + step, ... up to `to`, always finishing exactly on `to`
+fn pinned_steps(from: f32, to: f32, step: f32) -> Vec<f32> {
+    let mut values = Vec::new();
+    let mut value = from;
+    while value < to - 0.001 {
+        values.push(value);
+        value += step;
+    }
+    values.push(to);
+    values
+}
+
+pub fn corners(problem: &Problem) -> Vec<Position> {
+    let (x_min, y_min, x_max, y_max) = usable_area(problem);
+    vec![
+        Position { x: x_min, y: y_min },
+        Position { x: x_max, y: y_min },
+        Position { x: x_min, y: y_max },
+        Position { x: x_max, y: y_max },
+    ]
+}
+
+// Points along every edge of the stage, as close to the audience as
+// musicians are allowed
+pub fn edge_rows(problem: &Problem) -> Vec<Position> {
+    let (x_min, y_min, x_max, y_max) = usable_area(problem);
+    let mut points = Vec::new();
+    for x in pinned_steps(x_min, x_max, EDGE_SPACING) {
+        points.push(Position { x, y: y_min });
+        points.push(Position { x, y: y_max });
+    }
+    for y in pinned_steps(y_min, y_max, EDGE_SPACING) {
+        points.push(Position { x: x_min, y });
+        points.push(Position { x: x_max, y });
+    }
+    points
+}
+
+// A hexagonal packing: rows of points `spacing` apart, each row shifted half
+// a step from the one before, which is the densest way to pack musicians.
+// Any two points are at least `spacing` apart, so a musician can stand on
+// every one
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Lattice {
+    pub spacing: f32,
+    // Rows run up the stage instead of across it
+    pub vertical: bool,
+    // How far along its row the first point sits, from 0 up to `spacing`
+    pub shift: f32,
+    // Start the rows from the top (or right) edge instead of the bottom (or
+    // left)
+    pub flip: bool,
+}
+
+impl Lattice {
+    pub fn new(spacing: f32) -> Lattice {
+        Lattice {
+            spacing,
+            vertical: false,
+            shift: 0.0,
+            flip: false,
+        }
+    }
+
+    pub fn points(&self, problem: &Problem) -> Vec<Position> {
+        let (x_min, y_min, x_max, y_max) = usable_area(problem);
+        // Work along (u) and across (v) the rows, then map back to x and y
+        let (u_min, u_max, v_min, v_max) = if self.vertical {
+            (y_min, y_max, x_min, x_max)
+        } else {
+            (x_min, x_max, y_min, y_max)
+        };
+        // Neighbours in a perfect packing are exactly `spacing` apart, and
+        // rounding to f32 coordinates can land either side of that. Each
+        // point is worked out from the origin in f64 so errors don't add up.
+        // Steps along a row come out exact for the stages we get, but the row
+        // height never does, so rows are spread a touch further apart
+        let step = self.spacing as f64;
+        let row_height = step * 3.0_f64.sqrt() / 2.0 + ROW_SLACK;
+        let (u_min, u_max, v_min, v_max) = (u_min as f64, u_max as f64, v_min as f64, v_max as f64);
+
+        let mut points = Vec::new();
+        let mut row = 0;
+        while row as f64 * row_height <= v_max - v_min {
+            let v = if self.flip {
+                v_max - row as f64 * row_height
+            } else {
+                v_min + row as f64 * row_height
+            };
+            let offset = (self.shift as f64 + if row % 2 == 1 { step / 2.0 } else { 0.0 }) % step;
+            let mut column = 0;
+            loop {
+                let u = u_min + offset + column as f64 * step;
+                if u > u_max {
+                    break;
+                }
+                let (u, v) = (u as f32, v as f32);
+                points.push(if self.vertical {
+                    Position { x: v, y: u }
+                } else {
+                    Position { x: u, y: v }
+                });
+                column += 1;
+            }
+            row += 1;
+        }
+        points
+    }
+}
+
+pub fn hexagonal(problem: &Problem, spacing: f32) -> Vec<Position> {
+    Lattice::new(spacing).points(problem)
+}
+
+// `count` points picked evenly from `points`, in order
+fn thin(points: Vec<Position>, count: usize) -> Vec<Position> {
+    if points.len() <= count {
+        return points;
+    }
+    (0..count)
+        .map(|i| points[i * points.len() / count])
+        .collect()
+}
+
+// Candidate positions for musicians: the corners, dense rows hugging each
+// edge, and a hexagonal packing of the whole stage. There are at most
+// `max_count` of them, or as many as there are musicians if that's more: the
+// packing is spread out to fit, but never so far that it can't seat every
+// musician by itself, so there is always room for the whole band. Whatever
+// room the packing leaves goes to the corners and edges, which are thinned
+// out if there's too little
+pub fn candidates(problem: &Problem, max_count: usize) -> Vec<Position> {
+    let needed = problem.musicians.len();
+    let cap = max_count.max(needed).max(1);
+    let corners = corners(problem);
+    let edges = edge_rows(problem);
+
+    let room = cap
+        .saturating_sub(corners.len() + edges.len())
+        .max(needed)
+        .max(1);
+    let mut spacing = MUSICIAN_SPACING;
+    if hexagonal(problem, spacing).len() > room {
+        // More spacing means fewer points, so search for the tightest packing
+        // that fits, as long as it still seats everyone
+        let (mut low, mut high) = (MUSICIAN_SPACING, MUSICIAN_SPACING);
+        while hexagonal(problem, high).len() > room {
+            high *= 2.0;
+        }
+        for _ in 0..20 {
+            let middle = (low + high) / 2.0;
+            if hexagonal(problem, middle).len() > room {
+                low = middle;
+            } else {
+                high = middle;
+            }
+        }
+        spacing = high;
+        while spacing > MUSICIAN_SPACING && hexagonal(problem, spacing).len() < needed {
+            spacing = (spacing * 0.95).max(MUSICIAN_SPACING);
+        }
+    }
+    let mut packing = hexagonal(problem, spacing);
+    packing.truncate(cap);
+
+    // Anything on the same spot as a point already in is left out before
+    // thinning, so it doesn't use up the room
+    let key = |p: &Position| ((p.x * 100.0).round() as i64, (p.y * 100.0).round() as i64);
+    let mut seen: HashSet<(i64, i64)> = packing.iter().map(key).collect();
+    let mut fresh = |points: Vec<Position>| -> Vec<Position> {
+        points.into_iter().filter(|p| seen.insert(key(p))).collect()
+    };
+    let corners = fresh(corners);
+    let edges = fresh(edges);
+
+    let left = cap - packing.len();
+    let mut points = thin(corners, left);
+    points.extend(thin(edges, left - points.len()));
+    points.extend(packing);
+    points
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::*;
+    use std::fs;
+
+    fn load(id: u32) -> Problem {
+        let path = format!(
+            "{}/problems/problem-{}.json",
+            env!("CARGO_MANIFEST_DIR"),
+            id
+        );
+        serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap()
+    }
+
+    // The closest two points get, checking only pairs near each other along x
+    fn closest(points: &[Position]) -> f32 {
+        let mut sorted = points.to_vec();
+        sorted.sort_by(|a, b| a.x.total_cmp(&b.x));
+        let mut closest = f32::INFINITY;
+        for (i, a) in sorted.iter().enumerate() {
+            for b in &sorted[i + 1..] {
+                if b.x - a.x >= MUSICIAN_SPACING {
+                    break;
+                }
+                closest = closest.min(distance(a, b));
+            }
+        }
+        closest
+    }
+
+    #[test]
+    fn lattice_points_are_spaced_on_real_problems() {
+        // Big bands, tall and wide stages, and stages far from the origin
+        // where f32 coordinates are coarsest
+        for id in [1, 9, 38, 55, 63] {
+            let problem = load(id);
+            let (x_min, y_min, x_max, y_max) = usable_area(&problem);
+            for vertical in [false, true] {
+                for quarter in 0..4 {
+                    for flip in [false, true] {
+                        let lattice = Lattice {
+                            spacing: MUSICIAN_SPACING,
+                            vertical,
+                            shift: quarter as f32 * MUSICIAN_SPACING / 4.0,
+                            flip,
+                        };
+                        let points = lattice.points(&problem);
+                        assert!(
+                            closest(&points) >= MUSICIAN_SPACING,
+                            "problem {} {:?}",
+                            id,
+                            lattice
+                        );
+                        assert!(points.iter().all(|p| {
+                            p.x >= x_min && p.x <= x_max && p.y >= y_min && p.y <= y_max
+                        }));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn candidates_keep_to_the_count() {
+        for id in [1, 33, 42, 55] {
+            let problem = load(id);
+            let needed = problem.musicians.len();
+            for max_count in [12, 100, 300, 1000, 2000] {
+                let points = candidates(&problem, max_count);
+                assert!(
+                    points.len() <= max_count.max(needed),
+                    "problem {} asked for {} got {}",
+                    id,
+                    max_count,
+                    points.len()
+                );
+                assert!(points.len() >= needed.min(max_count));
+            }
+        }
+    }
+}
@@ -0,0 +1,27 @@
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+// A time limit for the whole process, for when whoever started it needs an
+// answer by then, like the portfolio does. Solvers with a time budget of
+// their own cut it short to fit, and the pipeline skips whatever stages are
+// left once it has passed
+static DEADLINE: OnceLock<Instant> = OnceLock::new();
+
+// Only the first limit set counts
+pub fn set(limit: Duration) {
+    let _ = DEADLINE.set(Instant::now() + limit);
+}
+
+// `wanted`, or however long is left before the deadline if that's less
+pub fn budget(wanted: Duration) -> Duration {
+    match DEADLINE.get() {
+        Some(deadline) => wanted.min(deadline.saturating_duration_since(Instant::now())),
+        None => wanted,
+    }
+}
+
+pub fn passed() -> bool {
+    DEADLINE
+        .get()
+        .is_some_and(|deadline| Instant::now() >= *deadline)
+}
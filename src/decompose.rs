@@ -0,0 +1,250 @@
+use crate::candidates::*;
+use crate::icfp::*;
+use crate::pipeline::*;
+use crate::scorer::*;
+use crate::solver::{assignment, blockers};
+use std::thread;
+
+// Parts of the stage are only filled this full, so the solver working on one
+// still has some choice about where its musicians go
+const FILL: f32 = 0.5;
+// Past this the parts get too small to be worth solving on their own
+const MAX_REGIONS: usize = 16;
+// No region gets more than this many times its fair share of musicians, so
+// the work is spread out even when one spot is better than the rest
+const MAX_SHARE: usize = 2;
+
+// A rectangle of the stage, as x_min, y_min, x_max, y_max
+type Region = (f32, f32, f32, f32);
+
+// How many parts a stage is worth cutting into: only as many as leave every
+// part room to seat the whole band
+fn region_count(problem: &Problem) -> usize {
+    let room = hexagonal(problem, MUSICIAN_SPACING).len() as f32 * FILL;
+    let parts = (room / problem.musicians.len().max(1) as f32) as usize;
+    parts.clamp(1, MAX_REGIONS)
+}
+
+// Cut the stage into a grid of about `count` rectangles, as close to square
+// as we can get them
+fn regions(problem: &Problem, count: usize) -> Vec<Region> {
+    let (width, height) = (problem.stage_width, problem.stage_height);
+    let rows = ((count as f32 * height / width).sqrt().round() as usize).clamp(1, count);
+    let columns = (count / rows).max(1);
+
+    let (left, bottom) = (problem.stage_bottom_left[0], problem.stage_bottom_left[1]);
+    let mut regions = Vec::new();
+    for row in 0..rows {
+        for column in 0..columns {
+            regions.push((
+                left + width * column as f32 / columns as f32,
+                bottom + height * row as f32 / rows as f32,
+                left + width * (column + 1) as f32 / columns as f32,
+                bottom + height * (row + 1) as f32 / rows as f32,
+            ));
+        }
+    }
+    regions
+}
+
+// A region as a stage of its own. Where it borders another region it
+// reaches half the musician spacing into its neighbor, so that after the
+// usual margin musicians in neighboring regions are still far enough apart
+fn sub_stage(problem: &Problem, region: &Region) -> Problem {
+    let (x_min, y_min, x_max, y_max) = *region;
+    let stage_left = problem.stage_bottom_left[0];
+    let stage_bottom = problem.stage_bottom_left[1];
+    let stage_right = stage_left + problem.stage_width;
+    let stage_top = stage_bottom + problem.stage_height;
+    let overlap = MUSICIAN_SPACING / 2.0;
+    let grow = |edge: f32, stage_edge: f32, by: f32| {
+        if (edge - stage_edge).abs() < 0.001 {
+            edge
+        } else {
+            edge + by
+        }
+    };
+
+    let left = grow(x_min, stage_left, -overlap);
+    let bottom = grow(y_min, stage_bottom, -overlap);
+    let right = grow(x_max, stage_right, overlap);
+    let top = grow(y_max, stage_top, overlap);
+    Problem {
+        stage_bottom_left: vec![left, bottom],
+        stage_width: right - left,
+        stage_height: top - bottom,
+        musicians: vec![],
+        attendees: vec![],
+        ..problem.clone()
+    }
+}
+
+fn closest_point(region: &Region, attendee: &Attendee) -> Position {
+    let (x_min, y_min, x_max, y_max) = *region;
+    Position {
+        x: attendee.x.clamp(x_min, x_max),
+        y: attendee.y.clamp(y_min, y_max),
+    }
+}
+
+fn square_distance(a: &Position, attendee: &Attendee) -> f32 {
+    (a.x - attendee.x).powi(2) + (a.y - attendee.y).powi(2)
+}
+
+// Each attendee goes to whichever region they're closest to, since that's
+// where most of what they hear will come from
+fn split_audience(problem: &Problem, regions: &[Region], parts: &mut [Problem]) {
+    for attendee in &problem.attendees {
+        let nearest = (0..regions.len())
+            .min_by(|&a, &b| {
+                let to_a = square_distance(&closest_point(&regions[a], attendee), attendee);
+                let to_b = square_distance(&closest_point(&regions[b], attendee), attendee);
+                to_a.total_cmp(&to_b)
+            })
+            .unwrap();
+        parts[nearest].attendees.push(attendee.clone());
+    }
+}
+
+// Roughly what each instrument is worth in each region: everyone's taste for
+// it, by how close they are
+fn region_values(problem: &Problem, regions: &[Region]) -> Vec<Vec<f64>> {
+    let instruments = problem.attendees.first().map_or(0, |a| a.tastes.len());
+    regions
+        .iter()
+        .map(|region| {
+            let (x_min, y_min, x_max, y_max) = *region;
+            let center = Position {
+                x: (x_min + x_max) / 2.0,
+                y: (y_min + y_max) / 2.0,
+            };
+            (0..instruments)
+                .map(|instrument| {
+                    problem
+                        .attendees
+                        .iter()
+                        .map(|attendee| impact(attendee, instrument as i64, &center) as f64)
+                        .sum()
+                })
+                .collect()
+        })
+        .collect()
+}
+
+// Send each musician to the region their instrument is worth most in, as
+// long as it has room left. The most valuable musicians choose first.
+// Returns which of the problem's musicians ended up in each region
+fn split_musicians(
+    problem: &Problem,
+    regions: &[Region],
+    parts: &mut [Problem],
+) -> Vec<Vec<usize>> {
+    let values = region_values(problem, regions);
+    let share = MAX_SHARE * (problem.musicians.len() / regions.len()).max(1);
+    let mut room: Vec<usize> = parts
+        .iter()
+        .map(|part| ((hexagonal(part, MUSICIAN_SPACING).len() as f32 * FILL) as usize).min(share))
+        .collect();
+
+    let best_value = |m: usize| {
+        let instrument = problem.musicians[m] as usize;
+        values
+            .iter()
+            .map(|region| region[instrument])
+            .fold(f64::MIN, f64::max)
+    };
+    let mut order: Vec<usize> = (0..problem.musicians.len()).collect();
+    order.sort_by(|&a, &b| best_value(b).total_cmp(&best_value(a)));
+
+    let mut members = vec![Vec::new(); regions.len()];
+    for m in order {
+        let instrument = problem.musicians[m] as usize;
+        // If every region is full, squeeze into the roomiest one
+        let region = (0..regions.len())
+            .filter(|&r| room[r] > 0)
+            .max_by(|&a, &b| values[a][instrument].total_cmp(&values[b][instrument]))
+            .unwrap_or_else(|| (0..regions.len()).max_by_key(|&r| room[r]).unwrap());
+        room[region] = room[region].saturating_sub(1);
+        members[region].push(m);
+        parts[region].musicians.push(problem.musicians[m]);
+    }
+    members
+}
+
+// Solve a huge stage in pieces: cut it into regions, give each region the
+// audience nearest to it and the musicians that audience likes best, and run
+// `stages` on every region at once. The regions overlap just enough that the
+// pieces fit back together without anyone standing too close.
+//
+// Each region was only solved for its own audience, so once stitched
+// together the whole stage gets two passes against everyone: musicians are
+// reassigned between the occupied spots, which also counts who now blocks
+// whom across region borders, and then anyone doing more harm than good is
+// moved out of the way. Nobody is moved to new spots to make the most of
+// other regions' audiences, so that's left to the region solvers' guesses
+pub fn solve(problem: &Problem, stages: &[(String, Stage)]) -> Solution {
+    let count = region_count(problem);
+    if count == 1 {
+        eprintln!("Stage is too small to split, solving it whole");
+        return run(problem, stages);
+    }
+
+    let regions = regions(problem, count);
+    let mut parts: Vec<Problem> = regions
+        .iter()
+        .map(|region| sub_stage(problem, region))
+        .collect();
+    split_audience(problem, &regions, &mut parts);
+    let members = split_musicians(problem, &regions, &mut parts);
+    for (i, part) in parts.iter().enumerate() {
+        eprintln!(
+            "Region {}: {}x{} stage, {} musicians, {} attendees",
+            i,
+            part.stage_width,
+            part.stage_height,
+            part.musicians.len(),
+            part.attendees.len()
+        );
+    }
+
+    // Scores for parts of the stage aren't scores for the problem
+    let solutions: Vec<Option<Solution>> = quietly(|| {
+        thread::scope(|scope| {
+            let handles: Vec<_> = parts
+                .iter()
+                .map(|part| {
+                    scope.spawn(move || {
+                        if part.musicians.is_empty() {
+                            None
+                        } else {
+                            Some(run(part, stages))
+                        }
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().expect("Region solver panicked"))
+                .collect()
+        })
+    });
+
+    let mut placements = vec![Position { x: 0.0, y: 0.0 }; problem.musicians.len()];
+    for (solution, members) in solutions.iter().zip(&members) {
+        if let Some(solution) = solution {
+            for (&m, position) in members.iter().zip(&solution.placements) {
+                placements[m] = *position;
+            }
+        }
+    }
+    let stitched = Solution { placements };
+    eprintln!(
+        "Stitched regions together for {}",
+        scorer(problem, &stitched)
+    );
+
+    let assigned = assignment::improve(problem, &stitched);
+    let solution = blockers::improve(problem, &assigned);
+    report(scorer(problem, &solution) as f64);
+    solution
+}
@@ -0,0 +1,356 @@
+use crate::candidates::*;
+use crate::geometry::*;
+use crate::icfp::*;
+use crate::scorer::*;
+use crate::shadow::*;
+
+// Keeps track of what is in the way between every musician and every
+// attendee, so the change in score from moving or swapping musicians can be
+// worked out without rescoring the whole solution
+#[derive(Clone)]
+pub struct DeltaScorer<'a> {
+    problem: &'a Problem,
+    attendees: Vec<Position>,
+    shadows: ShadowMap,
+    placements: Placements,
+    // Attendees sorted by angle as seen from each musician
+    around: Vec<Vec<(f32, u32)>>,
+    // How many musicians and pillars are between each musician and each
+    // attendee
+    blocked: Vec<Vec<u16>>,
+    score: f64,
+}
+
+impl<'a> DeltaScorer<'a> {
+    pub fn new(problem: &'a Problem, solution: &Solution) -> DeltaScorer<'a> {
+        let attendees: Vec<Position> = problem
+            .attendees
+            .iter()
+            .map(|a| Position { x: a.x, y: a.y })
+            .collect();
+        let mut delta = DeltaScorer {
+            problem,
+            attendees,
+            shadows: ShadowMap::new(problem),
+            placements: solution.placements.clone(),
+            around: Vec::new(),
+            blocked: Vec::new(),
+            score: 0.0,
+        };
+        for m in 0..delta.placements.len() {
+            let (around, blocked) = delta.sight_lines(m, &delta.placements[m]);
+            delta.around.push(around);
+            delta.blocked.push(blocked);
+        }
+        delta.score = (0..delta.placements.len())
+            .map(|m| delta.contribution(m))
+            .sum();
+        delta
+    }
+
+    // Raw score, without checking the placement is legal
+    pub fn score(&self) -> f64 {
+        self.score
+    }
+
+    pub fn placements(&self) -> &Placements {
+        &self.placements
+    }
+
+    pub fn solution(&self) -> Solution {
+        Solution {
+            placements: self.placements.clone(),
+        }
+    }
+
+    // What one musician currently adds to the score
+    pub fn contribution(&self, m: usize) -> f64 {
+        self.value(m, &self.placements[m], &self.blocked[m])
+    }
+
+    fn value(&self, m: usize, at: &Position, blocked: &[u16]) -> f64 {
+        let instrument = self.problem.musicians[m];
+        self.problem
+            .attendees
+            .iter()
+            .zip(blocked)
+            .filter(|(_, &blocked)| blocked == 0)
+            .map(|(attendee, _)| impact(attendee, instrument, at) as f64)
+            .sum()
+    }
+
+    // Attendees by angle from `at`, and what blocks each of them, for
+    // musician `m` standing there (so not counting `m` itself)
+    fn sight_lines(&self, m: usize, at: &Position) -> (Vec<(f32, u32)>, Vec<u16>) {
+        let around = sorted_by_angle(at, &self.attendees);
+        let mut blocked = vec![0; self.attendees.len()];
+        for (k, center) in self.placements.iter().enumerate() {
+            if k == m {
+                continue;
+            }
+            in_window(
+                &around,
+                angle(at, center),
+                window_width(MUSICIAN_RADIUS, distance(at, center)),
+                |a| {
+                    if is_blocked(at, &self.attendees[a], center, MUSICIAN_RADIUS) {
+                        blocked[a] += 1;
+                    }
+                },
+            );
+        }
+        for (a, blocked) in blocked.iter_mut().enumerate() {
+            if !self.shadows.is_visible(a, at) {
+                *blocked += 1;
+            }
+        }
+        (around, blocked)
+    }
+
+    // How the blocker counts from musician `k` change if a musician moves
+    // from `from` to `to`
+    fn blocker_changes(&self, k: usize, from: &Position, to: &Position) -> Vec<(usize, i32)> {
+        let at = &self.placements[k];
+        let mut changes: Vec<(usize, i32)> = Vec::new();
+        for (center, change) in [(from, -1), (to, 1)] {
+            in_window(
+                &self.around[k],
+                angle(at, center),
+                window_width(MUSICIAN_RADIUS, distance(at, center)),
+                |a| {
+                    if is_blocked(at, &self.attendees[a], center, MUSICIAN_RADIUS) {
+                        match changes.iter_mut().find(|(b, _)| *b == a) {
+                            Some((_, total)) => *total += change,
+                            None => changes.push((a, change)),
+                        }
+                    }
+                },
+            );
+        }
+        changes
+    }
+
+    // Change in score from moving musician `m` to `to`
+    pub fn move_delta(&self, m: usize, to: &Position) -> f64 {
+        let from = self.placements[m];
+        let mut delta = -self.contribution(m);
+
+        for k in 0..self.placements.len() {
+            if k == m {
+                continue;
+            }
+            let instrument = self.problem.musicians[k];
+            for (a, change) in self.blocker_changes(k, &from, to) {
+                let before = self.blocked[k][a] as i32;
+                let after = before + change;
+                let attendee = &self.problem.attendees[a];
+                if before == 0 && after > 0 {
+                    delta -= impact(attendee, instrument, &self.placements[k]) as f64;
+                } else if before > 0 && after == 0 {
+                    delta += impact(attendee, instrument, &self.placements[k]) as f64;
+                }
+            }
+        }
+
+        let (_, blocked) = self.sight_lines(m, to);
+        delta + self.value(m, to, &blocked)
+    }
+
+    pub fn move_to(&mut self, m: usize, to: &Position) {
+        let from = self.placements[m];
+        let delta = self.move_delta(m, to);
+        for k in 0..self.placements.len() {
+            if k == m {
+                continue;
+            }
+            for (a, change) in self.blocker_changes(k, &from, to) {
+                self.blocked[k][a] = (self.blocked[k][a] as i32 + change) as u16;
+            }
+        }
+        self.placements[m] = *to;
+        let (around, blocked) = self.sight_lines(m, to);
+        self.around[m] = around;
+        self.blocked[m] = blocked;
+        self.score += delta;
+    }
+
+    // What the musician at `m`'s spot would add if they played `instrument`
+    // instead
+    pub fn value_as(&self, m: usize, instrument: i64) -> f64 {
+        self.problem
+            .attendees
+            .iter()
+            .zip(&self.blocked[m])
+            .filter(|(_, &blocked)| blocked == 0)
+            .map(|(attendee, _)| impact(attendee, instrument, &self.placements[m]) as f64)
+            .sum()
+    }
+
+    // Change in score from musicians `a` and `b` trading places. The same
+    // spots stay occupied, so nobody's lines of sight change
+    pub fn swap_delta(&self, a: usize, b: usize) -> f64 {
+        let musicians = &self.problem.musicians;
+        if musicians[a] == musicians[b] {
+            return 0.0;
+        }
+        self.value_as(a, musicians[b]) + self.value_as(b, musicians[a])
+            - self.value_as(a, musicians[a])
+            - self.value_as(b, musicians[b])
+    }
+
+    pub fn swap(&mut self, a: usize, b: usize) {
+        self.score += self.swap_delta(a, b);
+        self.placements.swap(a, b);
+        self.around.swap(a, b);
+        self.blocked.swap(a, b);
+    }
+
+    // Whether musician `m` could legally stand at `to`: on the stage and far
+    // enough from everyone else
+    pub fn can_move(&self, m: usize, to: &Position) -> bool {
+        let (x_min, y_min, x_max, y_max) = usable_area(self.problem);
+        to.x >= x_min
+            && to.x <= x_max
+            && to.y >= y_min
+            && to.y <= y_max
+            && self
+                .placements
+                .iter()
+                .enumerate()
+                .all(|(k, p)| k == m || distance(p, to) >= MUSICIAN_SPACING)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    fn random_problem(rng: &mut StdRng) -> Problem {
+        let mut problem = Problem {
+            room_width: 400.0,
+            room_height: 400.0,
+            stage_width: 150.0,
+            stage_height: 150.0,
+            stage_bottom_left: vec![125.0, 125.0],
+            musicians: (0..12).map(|m| m % 3).collect(),
+            ..Problem::default()
+        };
+        while problem.attendees.len() < 60 {
+            let (x, y) = (rng.gen_range(0.0..400.0), rng.gen_range(0.0..400.0));
+            if !(100.0..300.0).contains(&x) || !(100.0..300.0).contains(&y) {
+                let tastes = (0..3).map(|_| rng.gen_range(-1000.0..1000.0)).collect();
+                problem.attendees.push(Attendee { x, y, tastes });
+            }
+        }
+        for center in [[60.0, 200.0], [200.0, 340.0], [330.0, 90.0]] {
+            problem.pillars.push(Pillar {
+                center: center.to_vec(),
+                radius: rng.gen_range(5.0..20.0),
+            });
+        }
+        problem
+    }
+
+    fn random_spot(rng: &mut StdRng, problem: &Problem) -> Position {
+        let (x_min, y_min, x_max, y_max) = usable_area(problem);
+        Position {
+            x: rng.gen_range(x_min..x_max),
+            y: rng.gen_range(y_min..y_max),
+        }
+    }
+
+    // Scored from scratch with the same geometry. The rapier scorer can
+    // disagree on lines of sight that only just graze a musician
+    fn full_score(problem: &Problem, placements: &Placements) -> f64 {
+        let hears = audible(problem, placements);
+        placements
+            .iter()
+            .enumerate()
+            .map(|(m, at)| {
+                problem
+                    .attendees
+                    .iter()
+                    .zip(&hears[m])
+                    .filter(|(_, &hears)| hears)
+                    .map(|(attendee, _)| impact(attendee, problem.musicians[m], at) as f64)
+                    .sum::<f64>()
+            })
+            .sum()
+    }
+
+    // Whether any musician's line to an attendee passes within rounding of
+    // the edge of another musician
+    fn grazes(problem: &Problem, placements: &Placements) -> bool {
+        problem.attendees.iter().any(|attendee| {
+            let to = Position {
+                x: attendee.x,
+                y: attendee.y,
+            };
+            placements.iter().any(|from| {
+                placements.iter().any(|center| {
+                    center != from
+                        && (segment_distance(from, &to, center) - MUSICIAN_RADIUS).abs() < 0.01
+                })
+            })
+        })
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() <= 1e-4 * a.abs().max(b.abs()).max(1.0)
+    }
+
+    #[test]
+    fn tracks_the_score_through_moves_and_swaps() {
+        let mut rng = StdRng::seed_from_u64(33);
+        for _ in 0..5 {
+            let problem = random_problem(&mut rng);
+            let mut placements: Placements = Vec::new();
+            while placements.len() < problem.musicians.len() {
+                let spot = random_spot(&mut rng, &problem);
+                if placements
+                    .iter()
+                    .all(|p| distance(p, &spot) >= MUSICIAN_SPACING)
+                {
+                    placements.push(spot);
+                }
+            }
+            let mut delta = DeltaScorer::new(&problem, &Solution { placements });
+            assert!(close(
+                delta.score(),
+                full_score(&problem, delta.placements())
+            ));
+
+            for _ in 0..100 {
+                let m = rng.gen_range(0..problem.musicians.len());
+                let before = delta.score();
+                if rng.gen_bool(0.5) {
+                    let to = random_spot(&mut rng, &problem);
+                    if !delta.can_move(m, &to) {
+                        continue;
+                    }
+                    let change = delta.move_delta(m, &to);
+                    delta.move_to(m, &to);
+                    assert!(close(delta.score(), before + change));
+                } else {
+                    let other = rng.gen_range(0..problem.musicians.len());
+                    let change = delta.swap_delta(m, other);
+                    delta.swap(m, other);
+                    assert!(close(delta.score(), before + change));
+                }
+
+                let fresh = DeltaScorer::new(&problem, &delta.solution());
+                assert!(close(delta.score(), fresh.score()));
+                assert!(close(
+                    delta.score(),
+                    full_score(&problem, delta.placements())
+                ));
+                if !grazes(&problem, delta.placements()) {
+                    let scored = scorer(&problem, &delta.solution()) as f64;
+                    assert!(close(delta.score(), scored));
+                }
+            }
+        }
+    }
+}
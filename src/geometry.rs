@@ -0,0 +1,79 @@
+use crate::icfp::*;
+use std::f32::consts::PI;
+
+pub fn distance(a: &Position, b: &Position) -> f32 {
+    ((a.x - b.x).powi(2) + (a.y - b.y).powi(2)).sqrt()
+}
+
+// Shortest distance from `point` to the segment between `from` and `to`
+pub fn segment_distance(from: &Position, to: &Position, point: &Position) -> f32 {
+    let (dx, dy) = (to.x - from.x, to.y - from.y);
+    let length_squared = dx * dx + dy * dy;
+    if length_squared == 0.0 {
+        return distance(from, point);
+    }
+    let t = (((point.x - from.x) * dx + (point.y - from.y) * dy) / length_squared).clamp(0.0, 1.0);
+    let closest = Position {
+        x: from.x + t * dx,
+        y: from.y + t * dy,
+    };
+    distance(&closest, point)
+}
+
+// A circle blocks the line between two points if the line passes through it.
+// Just touching the edge doesn't count
+pub fn is_blocked(from: &Position, to: &Position, center: &Position, radius: f32) -> bool {
+    segment_distance(from, to, center) < radius
+}
+
+// Angle of `to` as seen from `from`, in -PI..=PI
+pub fn angle(from: &Position, to: &Position) -> f32 {
+    (to.y - from.y).atan2(to.x - from.x)
+}
+
+// Indexes of `targets` sorted by their angle as seen from `from`, ready for
+// `in_window`
+pub fn sorted_by_angle(from: &Position, targets: &[Position]) -> Vec<(f32, u32)> {
+    let mut around: Vec<(f32, u32)> = targets
+        .iter()
+        .enumerate()
+        .map(|(i, target)| (angle(from, target), i as u32))
+        .collect();
+    around.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+    around
+}
+
+// How far either side of the direction to a circle we need to look for lines
+// of sight that might pass through it
+pub fn window_width(radius: f32, distance: f32) -> f32 {
+    if distance <= radius {
+        PI
+    } else {
+        (radius / distance).asin() + 0.0001
+    }
+}
+
+// Visit the attendees in `around` (sorted by angle) that are within `width`
+// radians either side of `direction`
+pub fn in_window(around: &[(f32, u32)], direction: f32, width: f32, mut visit: impl FnMut(usize)) {
+    let mut scan = |from: f32, to: f32| {
+        let start = around.partition_point(|&(angle, _)| angle < from);
+        for &(angle, attendee) in &around[start..] {
+            if angle > to {
+                break;
+            }
+            visit(attendee as usize);
+        }
+    };
+    if width >= PI {
+        scan(-PI, PI);
+        return;
+    }
+    scan(direction - width, direction + width);
+    if direction - width < -PI {
+        scan(direction - width + 2.0 * PI, PI);
+    }
+    if direction + width > PI {
+        scan(-PI, direction + width - 2.0 * PI);
+    }
+}
@@ -0,0 +1,423 @@
+use crate::candidates::*;
+use crate::geometry::*;
+use crate::icfp::*;
+use crate::scorer::*;
+use crate::shadow::*;
+use crate::symmetry::*;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+// Blocking is only modelled between points this close together. Those are
+// the musicians casting the widest shadows, and counting every pair would
+// make the model far too big
+const BLOCKING_RANGE: f32 = 30.0;
+// How many terms to write on one line, to keep lines short enough for every
+// solver to read
+const TERMS_PER_LINE: usize = 8;
+
+// Which instrument can go at which point: the candidate points, and the
+// musicians playing each instrument. Exporting and importing a model for a
+// problem must use the same layout, which comes down to asking for the same
+// number of points
+pub struct Layout {
+    pub points: Vec<Position>,
+    pub instruments: Vec<i64>,
+    // The musicians playing each of `instruments`
+    pub groups: Vec<Vec<usize>>,
+}
+
+impl Layout {
+    pub fn new(problem: &Problem, max_points: usize) -> Layout {
+        let groups = groups(problem);
+        Layout {
+            points: candidates(problem, max_points),
+            instruments: groups.iter().map(|g| problem.musicians[g[0]]).collect(),
+            groups,
+        }
+    }
+
+    // Whether a musician playing instrument `k` stands at point `p`
+    fn place(k: usize, p: usize) -> String {
+        format!("x_{}_{}", k, p)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Sense {
+    AtMost,
+    AtLeast,
+    Exactly,
+}
+
+pub struct Constraint {
+    pub name: String,
+    pub terms: Vec<(usize, f64)>,
+    pub sense: Sense,
+    pub rhs: f64,
+}
+
+// A model to maximize. Every variable is between 0 and 1, and binary ones
+// are 0 or 1
+pub struct Model {
+    pub variables: Vec<(String, bool)>,
+    pub objective: Vec<(usize, f64)>,
+    pub constraints: Vec<Constraint>,
+}
+
+impl Model {
+    fn variable(&mut self, name: String, binary: bool) -> usize {
+        self.variables.push((name, binary));
+        self.variables.len() - 1
+    }
+
+    fn constrain(&mut self, name: String, terms: Vec<(usize, f64)>, sense: Sense, rhs: f64) {
+        self.constraints.push(Constraint {
+            name,
+            terms,
+            sense,
+            rhs,
+        });
+    }
+}
+
+// The placement problem as an integer program, with one binary variable per
+// instrument per candidate point:
+//
+// - each instrument is played at as many points as it has musicians
+// - each point holds at most one musician, and points closer together than
+//   the musician spacing hold at most one between them
+// - a musician is worth what it would be to everyone who can see it past the
+//   pillars
+// - a musician standing near another's point takes away what the other
+//   would get from the attendees it blocks. This uses one extra variable per
+//   pair, forced to 1 when both points are used. Where two musicians block
+//   the same attendee it's counted twice, so it's only an approximation
+pub fn formulate(problem: &Problem, layout: &Layout) -> Model {
+    let mut model = Model {
+        variables: Vec::new(),
+        objective: Vec::new(),
+        constraints: Vec::new(),
+    };
+    let shadows = ShadowMap::new(problem);
+    let attendees: Vec<Position> = problem
+        .attendees
+        .iter()
+        .map(|a| Position { x: a.x, y: a.y })
+        .collect();
+
+    // x[k][p]
+    let x: Vec<Vec<usize>> = (0..layout.instruments.len())
+        .map(|k| {
+            (0..layout.points.len())
+                .map(|p| model.variable(Layout::place(k, p), true))
+                .collect()
+        })
+        .collect();
+    // Everyone at each point, whatever they play
+    let used = |p: usize| -> Vec<(usize, f64)> { x.iter().map(|row| (row[p], 1.0)).collect() };
+
+    for (k, group) in layout.groups.iter().enumerate() {
+        let terms = x[k].iter().map(|&v| (v, 1.0)).collect();
+        model.constrain(
+            format!("count_{}", k),
+            terms,
+            Sense::Exactly,
+            group.len() as f64,
+        );
+    }
+    for p in 0..layout.points.len() {
+        model.constrain(format!("point_{}", p), used(p), Sense::AtMost, 1.0);
+    }
+    for p in 0..layout.points.len() {
+        for q in p + 1..layout.points.len() {
+            if distance(&layout.points[p], &layout.points[q]) < MUSICIAN_SPACING {
+                let mut terms = used(p);
+                terms.extend(used(q));
+                model.constrain(format!("spacing_{}_{}", p, q), terms, Sense::AtMost, 1.0);
+            }
+        }
+    }
+
+    let visible: Vec<Vec<usize>> = layout
+        .points
+        .iter()
+        .map(|point| {
+            (0..attendees.len())
+                .filter(|&a| shadows.is_visible(a, point))
+                .collect()
+        })
+        .collect();
+
+    for (k, &instrument) in layout.instruments.iter().enumerate() {
+        for (p, point) in layout.points.iter().enumerate() {
+            let value: f64 = visible[p]
+                .iter()
+                .map(|&a| impact(&problem.attendees[a], instrument, point) as f64)
+                .sum();
+            model.objective.push((x[k][p], value));
+        }
+    }
+
+    for (p, point) in layout.points.iter().enumerate() {
+        for (q, blocker) in layout.points.iter().enumerate() {
+            let apart = distance(point, blocker);
+            if q == p || !(MUSICIAN_SPACING..=BLOCKING_RANGE).contains(&apart) {
+                continue;
+            }
+            let blocked: Vec<usize> = visible[p]
+                .iter()
+                .copied()
+                .filter(|&a| is_blocked(point, &attendees[a], blocker, MUSICIAN_RADIUS))
+                .collect();
+            if blocked.is_empty() {
+                continue;
+            }
+
+            for (k, &instrument) in layout.instruments.iter().enumerate() {
+                let lost: f64 = blocked
+                    .iter()
+                    .map(|&a| impact(&problem.attendees[a], instrument, point) as f64)
+                    .sum();
+                if lost == 0.0 {
+                    continue;
+                }
+                let both = model.variable(format!("b_{}_{}_{}", k, p, q), false);
+                model.objective.push((both, -lost));
+
+                if lost > 0.0 {
+                    // Worth avoiding, so only needs pushing up when both are used
+                    let mut terms = vec![(both, 1.0), (x[k][p], -1.0)];
+                    terms.extend(used(q).into_iter().map(|(v, _)| (v, -1.0)));
+                    model.constrain(
+                        format!("both_{}_{}_{}", k, p, q),
+                        terms,
+                        Sense::AtLeast,
+                        -1.0,
+                    );
+                } else {
+                    // Worth having, so needs holding down unless both are used
+                    model.constrain(
+                        format!("mine_{}_{}_{}", k, p, q),
+                        vec![(both, 1.0), (x[k][p], -1.0)],
+                        Sense::AtMost,
+                        0.0,
+                    );
+                    let mut terms = vec![(both, 1.0)];
+                    terms.extend(used(q).into_iter().map(|(v, _)| (v, -1.0)));
+                    model.constrain(
+                        format!("theirs_{}_{}_{}", k, p, q),
+                        terms,
+                        Sense::AtMost,
+                        0.0,
+                    );
+                }
+            }
+        }
+    }
+
+    model
+}
+
+fn write_terms(out: &mut String, model: &Model, terms: &[(usize, f64)]) {
+    for (i, (v, coefficient)) in terms.iter().enumerate() {
+        if i > 0 && i % TERMS_PER_LINE == 0 {
+            out.push_str("\n   ");
+        }
+        let sign = if *coefficient < 0.0 { '-' } else { '+' };
+        write!(
+            out,
+            " {} {} {}",
+            sign,
+            coefficient.abs(),
+            model.variables[*v].0
+        )
+        .unwrap();
+    }
+}
+
+// CPLEX LP format, which most solvers read
+pub fn to_lp(model: &Model) -> String {
+    let mut out = String::new();
+    out.push_str("\\ Musician placement\nMaximize\n obj:");
+    write_terms(&mut out, model, &model.objective);
+    out.push_str("\nSubject To\n");
+    for constraint in &model.constraints {
+        write!(out, " {}:", constraint.name).unwrap();
+        write_terms(&mut out, model, &constraint.terms);
+        let sense = match constraint.sense {
+            Sense::AtMost => "<=",
+            Sense::AtLeast => ">=",
+            Sense::Exactly => "=",
+        };
+        writeln!(out, " {} {}", sense, constraint.rhs).unwrap();
+    }
+    out.push_str("Bounds\n");
+    for (name, binary) in &model.variables {
+        if !binary {
+            writeln!(out, " 0 <= {} <= 1", name).unwrap();
+        }
+    }
+    out.push_str("Binary\n");
+    for (name, binary) in &model.variables {
+        if *binary {
+            writeln!(out, " {}", name).unwrap();
+        }
+    }
+    out.push_str("End\n");
+    out
+}
+
+// Free-format MPS. Not every solver understands OBJSENSE, so the objective
+// is negated and minimized instead
+pub fn to_mps(model: &Model) -> String {
+    let mut columns: Vec<Vec<(String, f64)>> = vec![Vec::new(); model.variables.len()];
+    for &(v, coefficient) in &model.objective {
+        columns[v].push(("obj".to_string(), -coefficient));
+    }
+    for constraint in &model.constraints {
+        for &(v, coefficient) in &constraint.terms {
+            columns[v].push((constraint.name.clone(), coefficient));
+        }
+    }
+
+    let mut out = String::new();
+    out.push_str("* Musician placement, objective negated\nNAME placement\nROWS\n N obj\n");
+    for constraint in &model.constraints {
+        let sense = match constraint.sense {
+            Sense::AtMost => "L",
+            Sense::AtLeast => "G",
+            Sense::Exactly => "E",
+        };
+        writeln!(out, " {} {}", sense, constraint.name).unwrap();
+    }
+
+    out.push_str("COLUMNS\n");
+    let mut integer = false;
+    for ((name, binary), entries) in model.variables.iter().zip(&columns) {
+        if *binary != integer {
+            let marker = if *binary { "INTORG" } else { "INTEND" };
+            writeln!(out, " MARKER 'MARKER' '{}'", marker).unwrap();
+            integer = *binary;
+        }
+        for (row, coefficient) in entries {
+            writeln!(out, " {} {} {}", name, row, coefficient).unwrap();
+        }
+    }
+    if integer {
+        out.push_str(" MARKER 'MARKER' 'INTEND'\n");
+    }
+
+    out.push_str("RHS\n");
+    for constraint in &model.constraints {
+        if constraint.rhs != 0.0 {
+            writeln!(out, " rhs {} {}", constraint.name, constraint.rhs).unwrap();
+        }
+    }
+    out.push_str("BOUNDS\n");
+    for (name, binary) in &model.variables {
+        if *binary {
+            writeln!(out, " BV bnd {}", name).unwrap();
+        } else {
+            writeln!(out, " UP bnd {} 1", name).unwrap();
+        }
+    }
+    out.push_str("ENDATA\n");
+    out
+}
+
+// Read the placement back out of a solver's solution file. Solvers all
+// write these differently, but they list each variable by name with its
+// value somewhere after it on the same line, which is all we look for
+pub fn read_solution(problem: &Problem, layout: &Layout, text: &str) -> Result<Solution, String> {
+    let mut places: HashMap<String, (usize, usize)> = HashMap::new();
+    for k in 0..layout.instruments.len() {
+        for p in 0..layout.points.len() {
+            places.insert(Layout::place(k, p), (k, p));
+        }
+    }
+
+    let mut chosen: Vec<Vec<usize>> = vec![Vec::new(); layout.instruments.len()];
+    for line in text.lines() {
+        let mut tokens = line.split_whitespace();
+        while let Some(token) = tokens.next() {
+            if let Some(&(k, p)) = places.get(token) {
+                let value = tokens.find_map(|t| t.parse::<f64>().ok());
+                if matches!(value, Some(value) if value > 0.5) {
+                    chosen[k].push(p);
+                }
+                break;
+            }
+        }
+    }
+
+    let mut placements = vec![Position { x: 0.0, y: 0.0 }; problem.musicians.len()];
+    for (k, group) in layout.groups.iter().enumerate() {
+        if chosen[k].len() != group.len() {
+            return Err(format!(
+                "Instrument {} has {} musicians but the solution puts it at {} points",
+                layout.instruments[k],
+                group.len(),
+                chosen[k].len()
+            ));
+        }
+        for (&m, &p) in group.iter().zip(&chosen[k]) {
+            placements[m] = layout.points[p];
+        }
+    }
+    Ok(Solution { placements })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The fixture solutions were written against this many points
+    const POINTS: usize = 12;
+
+    fn fixture(name: &str) -> String {
+        let filename = format!("{}/fixtures/ilp/{}", env!("CARGO_MANIFEST_DIR"), name);
+        std::fs::read_to_string(&filename).unwrap()
+    }
+
+    fn problem() -> Problem {
+        serde_json::from_str(&fixture("problem.json")).unwrap()
+    }
+
+    #[test]
+    fn model_places_every_instrument_at_every_point() {
+        let problem = problem();
+        let layout = Layout::new(&problem, POINTS);
+        assert_eq!(layout.points.len(), POINTS);
+        let model = formulate(&problem, &layout);
+        let places = model
+            .variables
+            .iter()
+            .filter(|(name, _)| name.starts_with("x_"))
+            .count();
+        assert_eq!(places, layout.instruments.len() * POINTS);
+    }
+
+    #[test]
+    fn reads_cbc_and_highs_solutions() {
+        let problem = problem();
+        let layout = Layout::new(&problem, POINTS);
+        assert_eq!(layout.points.len(), POINTS);
+        let expected = vec![
+            Position { x: 40.0, y: 50.0 },
+            Position { x: 60.0, y: 40.0 },
+            Position { x: 40.0, y: 40.0 },
+        ];
+        for name in ["cbc.sol", "highs.sol"] {
+            let solution = read_solution(&problem, &layout, &fixture(name)).unwrap();
+            assert_eq!(solution.placements, expected, "{}", name);
+        }
+    }
+
+    #[test]
+    fn rejects_solutions_missing_musicians() {
+        let problem = problem();
+        let layout = Layout::new(&problem, POINTS);
+        let text = "x_0_9 1\nx_1_0 1\n";
+        assert!(read_solution(&problem, &layout, text).is_err());
+    }
+}
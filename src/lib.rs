@@ -1,10 +1,36 @@
+pub mod candidates;
+pub mod deadline;
+pub mod decompose;
+pub mod delta;
+pub mod geometry;
 pub mod icfp;
+pub mod ilp;
+pub mod params;
+pub mod pipeline;
+pub mod preview;
+pub mod repair;
 pub mod scorer;
+pub mod shadow;
+pub mod surrogate;
+pub mod symmetry;
 pub mod solver {
+    pub mod assignment;
+    pub mod beam;
+    pub mod blockers;
+    pub mod cluster;
+    pub mod cmaes;
+    pub mod exhaustive;
     pub mod ga;
+    pub mod gradient;
+    pub mod greedy;
     pub mod gravity_of_n;
     pub mod hot_cold;
+    pub mod islands;
+    pub mod lattice;
+    pub mod lns;
+    pub mod pso;
     pub mod random;
     pub mod random_of_n;
+    pub mod tabu;
     pub mod trivial;
 }
@@ -0,0 +1,188 @@
+use crate::icfp::*;
+use crate::solver::*;
+use rand::Rng;
+use std::fmt;
+
+// One tunable solver parameter and the range worth searching, which is also
+// all the solver will accept
+#[derive(Debug, Clone, Copy)]
+pub struct Param {
+    pub name: &'static str,
+    pub min: f64,
+    pub max: f64,
+    pub default: f64,
+    pub integer: bool,
+}
+
+// Values for every parameter in a solver's schema
+#[derive(Debug, Clone, PartialEq)]
+pub struct Params {
+    values: Vec<(&'static str, f64)>,
+}
+
+impl Params {
+    pub fn defaults(schema: &[Param]) -> Params {
+        Params {
+            values: schema.iter().map(|p| (p.name, p.default)).collect(),
+        }
+    }
+
+    // Uniformly random within each parameter's range
+    pub fn sample(schema: &[Param], rng: &mut impl Rng) -> Params {
+        Params {
+            values: schema
+                .iter()
+                .map(|p| {
+                    let value = rng.gen_range(p.min..=p.max);
+                    (p.name, if p.integer { value.round() } else { value })
+                })
+                .collect(),
+        }
+    }
+
+    // The defaults, with some of them changed by settings like
+    // "population=200:survivors=0.3". Values outside a parameter's range are
+    // an error rather than something the solver has to cope with
+    pub fn parse(schema: &[Param], settings: &str) -> Result<Params, String> {
+        let mut params = Params::defaults(schema);
+        for setting in settings.split(':').filter(|s| !s.is_empty()) {
+            let (name, value) = setting
+                .split_once('=')
+                .ok_or_else(|| format!("Expected name=value, got {}", setting))?;
+            let param = schema
+                .iter()
+                .find(|p| p.name == name)
+                .ok_or_else(|| format!("No parameter called {}", name))?;
+            let value: f64 = value
+                .parse()
+                .map_err(|_| format!("{} needs a number, got {}", name, value))?;
+            let value = if param.integer { value.round() } else { value };
+            if !(param.min..=param.max).contains(&value) {
+                return Err(format!(
+                    "{} needs to be between {} and {}, got {}",
+                    name, param.min, param.max, value
+                ));
+            }
+            for (n, v) in params.values.iter_mut() {
+                if *n == name {
+                    *v = value;
+                }
+            }
+        }
+        Ok(params)
+    }
+
+    pub fn get(&self, name: &str) -> f64 {
+        match self.values.iter().find(|(n, _)| *n == name) {
+            Some((_, value)) => *value,
+            None => panic!("No parameter called {}", name),
+        }
+    }
+
+    pub fn count(&self, name: &str) -> usize {
+        self.get(name).max(0.0) as usize
+    }
+}
+
+impl fmt::Display for Params {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let values: Vec<String> = self
+            .values
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect();
+        write!(f, "{}", values.join(" "))
+    }
+}
+
+pub type TunableSolver = fn(&Problem, &Params) -> Solution;
+pub type TunablePass = fn(&Problem, &Solution, &Params) -> Solution;
+
+// The solvers that can be tuned, with their parameter schemas
+pub fn tunable(name: &str) -> Option<(&'static [Param], TunableSolver)> {
+    match name {
+        "beam" => Some((beam::PARAMS, beam::solve_with_params)),
+        "ga" => Some((ga::PARAMS, ga::solve_with_params)),
+        "gravity-of-n" => Some((gravity_of_n::PARAMS, gravity_of_n::solve_with_params)),
+        "islands" => Some((islands::PARAMS, islands::solve_with_params)),
+        "lns" => Some((lns::PARAMS, lns::solve_with_params)),
+        "pso" => Some((pso::PARAMS, pso::solve_with_params)),
+        "random-best" => Some((random_of_n::PARAMS, random_of_n::solve_with_params)),
+        "tabu" => Some((tabu::PARAMS, tabu::solve_with_params)),
+        _ => None,
+    }
+}
+
+// Post-passes that take parameters
+pub fn tunable_pass(name: &str) -> Option<(&'static [Param], TunablePass)> {
+    match name {
+        "lns" => Some((lns::PARAMS, lns::improve_with_params)),
+        "tabu" => Some((tabu::PARAMS, tabu::improve_with_params)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    const TUNABLE: [&str; 8] = [
+        "beam",
+        "ga",
+        "gravity-of-n",
+        "islands",
+        "lns",
+        "pso",
+        "random-best",
+        "tabu",
+    ];
+
+    #[test]
+    fn schemas_are_sane() {
+        for name in TUNABLE {
+            let (schema, _) = tunable(name).unwrap();
+            for (i, param) in schema.iter().enumerate() {
+                assert!(param.min <= param.default && param.default <= param.max);
+                assert!(schema[..i].iter().all(|p| p.name != param.name));
+            }
+        }
+    }
+
+    #[test]
+    fn parse_changes_only_what_is_given() {
+        let (schema, _) = tunable("islands").unwrap();
+        let params = Params::parse(schema, "topology=1:migrants=7.6").unwrap();
+        assert_eq!(params.count("topology"), 1);
+        assert_eq!(params.get("migrants"), 8.0);
+        assert_eq!(params.get("population"), 100.0);
+
+        assert!(Params::parse(schema, "").is_ok());
+        assert!(Params::parse(schema, "bogus=1").is_err());
+        assert!(Params::parse(schema, "topology").is_err());
+        assert!(Params::parse(schema, "topology=ring").is_err());
+    }
+
+    #[test]
+    fn parse_rejects_values_out_of_range() {
+        let (schema, _) = tunable("ga").unwrap();
+        assert!(Params::parse(schema, "population=0").is_err());
+        assert!(Params::parse(schema, "population=501").is_err());
+        assert!(Params::parse(schema, "population=NaN").is_err());
+        assert!(Params::parse(schema, "population=10").is_ok());
+
+        let (schema, _) = tunable("random-best").unwrap();
+        assert!(Params::parse(schema, "attempts=0").is_err());
+    }
+
+    #[test]
+    fn survivors_never_outnumber_the_population() {
+        let mut rng = StdRng::seed_from_u64(42);
+        for _ in 0..1000 {
+            let params = Params::sample(ga::PARAMS, &mut rng);
+            let survivors = ga::survivor_count(&params);
+            assert!(survivors >= 1 && survivors <= params.count("population"));
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use crate::deadline;
+use crate::decompose;
+use crate::icfp::*;
+use crate::params::*;
+use crate::repair::*;
+use crate::scorer::*;
+use crate::solver::*;
+use std::time::Instant;
+
+// Every stage takes the problem and the solution so far, and returns a
+// (hopefully) better one
+pub type Stage = Box<dyn Fn(&Problem, &Solution) -> Solution + Sync>;
+type Plain = fn(&Problem, &Solution) -> Solution;
+
+// Solvers that build a solution from nothing. They ignore whatever came
+// before, so they only make sense as the first stage
+fn start(name: &str) -> Option<Stage> {
+    let stage: Plain = match name {
+        "trivial" => |problem, _| trivial::solve(problem),
+        "random" => |problem, _| random::solve(problem),
+        "random-best" => |problem, _| random_of_n::solve(problem),
+        "gravity-of-n" => |problem, _| gravity_of_n::solve(problem),
+        "hot-cold" => |problem, _| hot_cold::solve(problem),
+        "ga" => |problem, _| ga::solve(problem),
+        "islands" => |problem, _| islands::solve(problem),
+        "cmaes" => |problem, _| cmaes::solve(problem),
+        "beam" => |problem, _| beam::solve(problem),
+        "cluster" => |problem, _| cluster::solve(problem),
+        "exhaustive" => |problem, _| exhaustive::solve(problem),
+        "gradient" => |problem, _| gradient::solve(problem),
+        "greedy" => |problem, _| greedy::solve(problem),
+        "lattice" => |problem, _| lattice::solve(problem),
+        "lns" => |problem, _| lns::solve(problem),
+        "pso" => |problem, _| pso::solve(problem),
+        "tabu" => |problem, _| tabu::solve(problem),
+        _ => return None,
+    };
+    Some(Box::new(stage))
+}
+
+// Passes that improve an existing solution
+fn improve(name: &str) -> Option<Stage> {
+    let stage: Plain = match name {
+        "assign" => assignment::improve,
+        "blockers" => blockers::improve,
+        "gradient" => gradient::improve,
+        "lns" => lns::improve,
+        "tabu" => tabu::improve,
+        _ => return None,
+    };
+    Some(Box::new(stage))
+}
+
+// A tunable solver or pass with some of its parameters set
+fn tuned(name: &str, settings: &str, first: bool) -> Result<Stage, String> {
+    if first {
+        if let Some((schema, solver)) = tunable(name) {
+            let params = Params::parse(schema, settings)?;
+            return Ok(Box::new(move |problem, _| solver(problem, &params)));
+        }
+    } else if let Some((schema, pass)) = tunable_pass(name) {
+        let params = Params::parse(schema, settings)?;
+        return Ok(Box::new(move |problem, solution| {
+            pass(problem, solution, &params)
+        }));
+    }
+    Err(format!("{} has no parameters to set", name))
+}
+
+// Look up the stages for a pipeline like "greedy,tabu,assign". The first
+// stage is a solver; the rest improve on what it found. Tunable stages can
+// have parameters set after their name, like "islands:topology=1:migrants=10"
+pub fn parse(pipeline: &[&str]) -> Result<Vec<(String, Stage)>, String> {
+    pipeline
+        .iter()
+        .enumerate()
+        .map(|(i, &spec)| {
+            let stage = match spec.split_once(':') {
+                Some((name, settings)) => Some(tuned(name, settings, i == 0)?),
+                None if i == 0 => start(spec),
+                None => improve(spec),
+            };
+            match stage {
+                Some(stage) => Ok((spec.to_string(), stage)),
+                None if i == 0 => Err(format!("Invalid solver {}", spec)),
+                None => Err(format!("Invalid post-pass {}", spec)),
+            }
+        })
+        .collect()
+}
+
+pub fn run(problem: &Problem, stages: &[(String, Stage)]) -> Solution {
+    let mut solution = Solution { placements: vec![] };
+    for (i, (name, stage)) in stages.iter().enumerate() {
+        // Whatever the first stage came up with has to do
+        if i > 0 && deadline::passed() {
+            eprintln!("Out of time, skipping stage {}", name);
+            continue;
+        }
+        eprintln!("Running stage {}", name);
+        let start = Instant::now();
+        solution = stage(problem, &solution);
+        let score = scorer(problem, &solution);
+        eprintln!(
+            "Stage {} took {:.1}s and scored {}",
+            name,
+            start.elapsed().as_secs_f32(),
+            score
+        );
+        report(score as f64);
+    }
+    solution
+}
+
+// Solve with a whole pipeline, given by name, like "greedy,tabu,assign". One
+// starting with "split" runs the rest on each part of a huge stage
+// separately, then puts the parts back together. Whatever the solvers come
+// up with is repaired, so what comes out is always legal
+pub fn solve(problem: &Problem, pipeline: &[&str]) -> Result<Solution, String> {
+    let (split, names) = match pipeline.split_first() {
+        Some((&"split", rest)) => (true, rest),
+        _ => (false, pipeline),
+    };
+    let names = if names.is_empty() {
+        &["trivial"]
+    } else {
+        names
+    };
+
+    let stages = parse(names)?;
+    let solution = if split {
+        decompose::solve(problem, &stages)
+    } else {
+        run(problem, &stages)
+    };
+
+    let repair = repair(problem, &solution)?;
+    for (m, moved) in repair.moved.iter().enumerate() {
+        if *moved > 0.0 {
+            eprintln!("Repair moved musician {} by {}", m, moved);
+        }
+    }
+    Ok(repair.solution)
+}
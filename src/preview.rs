@@ -0,0 +1,159 @@
+use crate::icfp::*;
+use crate::scorer::*;
+
+// Attendee happiness, from least to most happy. Unhappy attendees get '-'.
+const HAPPINESS_RAMP: [char; 4] = ['.', ':', '+', '@'];
+
+fn instrument_char(instrument: i64) -> char {
+    match instrument {
+        0..=9 => (b'0' + instrument as u8) as char,
+        10..=35 => (b'a' + (instrument - 10) as u8) as char,
+        36..=61 => (b'A' + (instrument - 36) as u8) as char,
+        _ => '#',
+    }
+}
+
+struct View {
+    x_min: f32,
+    y_min: f32,
+    x_max: f32,
+    y_max: f32,
+    cols: usize,
+    rows: usize,
+}
+
+impl View {
+    // Frame the stage plus half a stage worth of room around it, so we see
+    // the attendees closest to the band
+    fn new(problem: &Problem, cols: usize) -> View {
+        let margin = problem.stage_width.max(problem.stage_height) / 2.0;
+        let x_min = (problem.stage_bottom_left[0] - margin).max(0.0);
+        let y_min = (problem.stage_bottom_left[1] - margin).max(0.0);
+        let x_max =
+            (problem.stage_bottom_left[0] + problem.stage_width + margin).min(problem.room_width);
+        let y_max =
+            (problem.stage_bottom_left[1] + problem.stage_height + margin).min(problem.room_height);
+
+        // Terminal cells are about twice as tall as they are wide
+        let scale = (x_max - x_min) / cols as f32;
+        let rows = ((y_max - y_min) / (scale * 2.0)).ceil().max(1.0) as usize;
+
+        View {
+            x_min,
+            y_min,
+            x_max,
+            y_max,
+            cols,
+            rows,
+        }
+    }
+
+    // Grid cell for a room coordinate, with row 0 at the top
+    fn cell(&self, x: f32, y: f32) -> Option<(usize, usize)> {
+        if x < self.x_min || x > self.x_max || y < self.y_min || y > self.y_max {
+            return None;
+        }
+        let col = ((x - self.x_min) / (self.x_max - self.x_min) * self.cols as f32) as usize;
+        let row = ((self.y_max - y) / (self.y_max - self.y_min) * self.rows as f32) as usize;
+        Some((col.min(self.cols - 1), row.min(self.rows - 1)))
+    }
+}
+
+// The room around the stage as text, `cols` characters wide: attendees by
+// how happy they are, the stage outline, pillars, and musicians by
+// instrument
+pub fn render(problem: &Problem, solution: &Solution, cols: usize) -> Vec<String> {
+    let view = View::new(problem, cols);
+    let mut grid = vec![vec![' '; view.cols]; view.rows];
+
+    // Attendees first, so everything on stage draws over them. Several
+    // attendees can land in one cell, so add their happiness up
+    let happiness = attendee_scores(problem, solution);
+    let mut cell_happiness = vec![vec![None; view.cols]; view.rows];
+    for (attendee, score) in problem.attendees.iter().zip(&happiness) {
+        if let Some((col, row)) = view.cell(attendee.x, attendee.y) {
+            let total: &mut Option<f32> = &mut cell_happiness[row][col];
+            *total = Some(total.unwrap_or(0.0) + score);
+        }
+    }
+    let happiest = cell_happiness
+        .iter()
+        .flatten()
+        .flatten()
+        .fold(0.0_f32, |a, &b| a.max(b));
+    for (row, cells) in cell_happiness.iter().enumerate() {
+        for (col, total) in cells.iter().enumerate() {
+            if let Some(total) = total {
+                grid[row][col] = if *total < 0.0 {
+                    '-'
+                } else if happiest <= 0.0 {
+                    HAPPINESS_RAMP[0]
+                } else {
+                    let step = (total / happiest * HAPPINESS_RAMP.len() as f32) as usize;
+                    HAPPINESS_RAMP[step.min(HAPPINESS_RAMP.len() - 1)]
+                };
+            }
+        }
+    }
+
+    // Stage outline
+    let stage_x_min = problem.stage_bottom_left[0];
+    let stage_y_min = problem.stage_bottom_left[1];
+    let stage_x_max = stage_x_min + problem.stage_width;
+    let stage_y_max = stage_y_min + problem.stage_height;
+    if let (Some((left, top)), Some((right, bottom))) = (
+        view.cell(stage_x_min, stage_y_max),
+        view.cell(stage_x_max, stage_y_min),
+    ) {
+        for row in [top, bottom] {
+            grid[row][left..=right].fill('-');
+        }
+        for cells in grid.iter_mut().take(bottom + 1).skip(top) {
+            cells[left] = '|';
+            cells[right] = '|';
+        }
+        for (col, row) in [(left, top), (right, top), (left, bottom), (right, bottom)] {
+            grid[row][col] = '+';
+        }
+    }
+
+    // Pillars cover every cell whose center falls inside them, and always at
+    // least the cell at their center
+    for pillar in &problem.pillars {
+        let (cx, cy, r) = (pillar.center[0], pillar.center[1], pillar.radius);
+        if let (Some((left, top)), Some((right, bottom))) = (
+            view.cell((cx - r).max(view.x_min), (cy + r).min(view.y_max)),
+            view.cell((cx + r).min(view.x_max), (cy - r).max(view.y_min)),
+        ) {
+            for (row, cells) in grid.iter_mut().enumerate().take(bottom + 1).skip(top) {
+                for (col, cell) in cells.iter_mut().enumerate().take(right + 1).skip(left) {
+                    let x = view.x_min
+                        + (col as f32 + 0.5) / view.cols as f32 * (view.x_max - view.x_min);
+                    let y = view.y_max
+                        - (row as f32 + 0.5) / view.rows as f32 * (view.y_max - view.y_min);
+                    if (x - cx).powi(2) + (y - cy).powi(2) <= r * r {
+                        *cell = 'O';
+                    }
+                }
+            }
+        }
+        if let Some((col, row)) = view.cell(cx, cy) {
+            grid[row][col] = 'O';
+        }
+    }
+
+    // Musicians last. Different instruments crammed into one cell show as '*'
+    let mut musician_cells = vec![vec![None; view.cols]; view.rows];
+    for (i, player) in solution.placements.iter().enumerate() {
+        if let Some((col, row)) = view.cell(player.x, player.y) {
+            let c = instrument_char(problem.musicians[i]);
+            musician_cells[row][col] = match musician_cells[row][col] {
+                Some(existing) if existing != c => Some('*'),
+                _ => Some(c),
+            };
+            grid[row][col] = musician_cells[row][col].unwrap();
+        }
+    }
+
+    grid.iter().map(|cells| cells.iter().collect()).collect()
+}
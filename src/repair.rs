@@ -0,0 +1,317 @@
+use crate::candidates::*;
+use crate::geometry::*;
+use crate::icfp::*;
+
+// Musicians too close together get nudged apart for this many rounds;
+// anyone still too close to someone is moved to a free candidate point
+const NUDGE_ROUNDS: usize = 3;
+// Nudge a little further than needed so rounding can't leave two musicians
+// just under the spacing
+const NUDGE_SLACK: f32 = 0.01;
+
+// Whether moving musician `m` to `to` would put it too close to anyone other
+// than `partner` it isn't already too close to, or anyone at all who was
+// fine to begin with
+fn crowds(placements: &Placements, fine: &[bool], m: usize, partner: usize, to: &Position) -> bool {
+    placements.iter().enumerate().any(|(k, p)| {
+        k != m
+            && k != partner
+            && distance(to, p) < MUSICIAN_SPACING
+            && (fine[k] || distance(&placements[m], p) >= MUSICIAN_SPACING)
+    })
+}
+
+// Make a placement legal again, only moving musicians that break the rules:
+// anyone off the stage margin is clamped back onto it, and each pair of them
+// too close together is nudged apart by the overlap, as long as that doesn't
+// crowd anyone else. After a few rounds of that, whoever is still too close
+// to someone is moved to the nearest free candidate point. If there's none
+// left, everyone starts over on a packed stage, and it's only an error if
+// even that can't seat them all
+pub fn repair_placements(
+    problem: &Problem,
+    placements: &mut Placements,
+    points: &[Position],
+) -> Result<(), String> {
+    let (x_min, y_min, x_max, y_max) = usable_area(problem);
+    let fine: Vec<bool> = placements
+        .iter()
+        .enumerate()
+        .map(|(m, at)| {
+            (x_min..=x_max).contains(&at.x)
+                && (y_min..=y_max).contains(&at.y)
+                && placements
+                    .iter()
+                    .enumerate()
+                    .all(|(k, p)| k == m || distance(p, at) >= MUSICIAN_SPACING)
+        })
+        .collect();
+    let clamp = |p: &mut Position| {
+        p.x = p.x.clamp(x_min, x_max);
+        p.y = p.y.clamp(y_min, y_max);
+    };
+    placements.iter_mut().for_each(clamp);
+
+    for _ in 0..NUDGE_ROUNDS {
+        let mut nudged = false;
+        for a in 0..placements.len() {
+            for b in a + 1..placements.len() {
+                let gap = distance(&placements[a], &placements[b]);
+                if gap >= MUSICIAN_SPACING || fine[a] || fine[b] {
+                    continue;
+                }
+                // Musicians on top of each other get nudged apart sideways
+                let (dx, dy) = if gap > 0.0 {
+                    (
+                        (placements[b].x - placements[a].x) / gap,
+                        (placements[b].y - placements[a].y) / gap,
+                    )
+                } else {
+                    (1.0, 0.0)
+                };
+                let push = (MUSICIAN_SPACING + NUDGE_SLACK - gap) / 2.0;
+                let mut to_a = Position {
+                    x: placements[a].x - dx * push,
+                    y: placements[a].y - dy * push,
+                };
+                let mut to_b = Position {
+                    x: placements[b].x + dx * push,
+                    y: placements[b].y + dy * push,
+                };
+                clamp(&mut to_a);
+                clamp(&mut to_b);
+                if distance(&to_a, &to_b) < MUSICIAN_SPACING
+                    || crowds(placements, &fine, a, b, &to_a)
+                    || crowds(placements, &fine, b, a, &to_b)
+                {
+                    continue;
+                }
+                placements[a] = to_a;
+                placements[b] = to_b;
+                nudged = true;
+            }
+        }
+        if !nudged {
+            break;
+        }
+    }
+
+    // Anyone who was fine is only too close to someone who wasn't, and who
+    // will be moved out of the way, so they stay put
+    for m in 0..placements.len() {
+        let clear = |at: &Position, placements: &Placements| {
+            placements
+                .iter()
+                .enumerate()
+                .all(|(k, p)| k == m || distance(p, at) >= MUSICIAN_SPACING)
+        };
+        if fine[m] || clear(&placements[m], placements) {
+            continue;
+        }
+        let from = placements[m];
+        let free = points
+            .iter()
+            .filter(|point| clear(point, placements))
+            .min_by(|a, b| distance(a, &from).total_cmp(&distance(b, &from)));
+        match free {
+            Some(free) => placements[m] = *free,
+            None => return start_over(problem, placements),
+        }
+    }
+    Ok(())
+}
+
+// Everyone on the tightest packing of the stage, in order
+fn start_over(problem: &Problem, placements: &mut Placements) -> Result<(), String> {
+    let count = placements.len();
+    let packing = hexagonal(problem, MUSICIAN_SPACING);
+    if packing.len() < count {
+        return Err(format!("No room for {} musicians on the stage", count));
+    }
+    eprintln!("No room left to repair into, starting over on a packed stage");
+    placements.copy_from_slice(&packing[..count]);
+    Ok(())
+}
+
+// A solution made legal, and how far each musician had to move for it
+pub struct Repair {
+    pub solution: Solution,
+    pub moved: Vec<f32>,
+}
+
+// Make any solution legal, moving musicians as little as we can (see
+// `repair_placements`). Musicians that were fine to begin with stay put,
+// unless the stage is so full that everyone has to start over. Musicians
+// left out can't be made up, so a solution missing some is an error
+pub fn repair(problem: &Problem, solution: &Solution) -> Result<Repair, String> {
+    if solution.placements.len() != problem.musicians.len() {
+        return Err(format!(
+            "{} placements for {} musicians",
+            solution.placements.len(),
+            problem.musicians.len()
+        ));
+    }
+    let mut placements = solution.placements.clone();
+    repair_placements(
+        problem,
+        &mut placements,
+        &candidates(problem, MAX_CANDIDATES),
+    )?;
+    let moved = solution
+        .placements
+        .iter()
+        .zip(&placements)
+        .map(|(before, after)| distance(before, after))
+        .collect();
+    Ok(Repair {
+        solution: Solution { placements },
+        moved,
+    })
+}
+
+// Everything that would get a solution rejected, described for people
+pub fn violations(problem: &Problem, solution: &Solution) -> Vec<String> {
+    let mut found = Vec::new();
+    let placements = &solution.placements;
+    if placements.len() != problem.musicians.len() {
+        found.push(format!(
+            "{} placements for {} musicians",
+            placements.len(),
+            problem.musicians.len()
+        ));
+    }
+
+    let (x_min, y_min, x_max, y_max) = usable_area(problem);
+    for (m, p) in placements.iter().enumerate() {
+        if p.x < x_min || p.x > x_max || p.y < y_min || p.y > y_max {
+            found.push(format!(
+                "Musician {} at ({}, {}) isn't at least {} inside the stage",
+                m, p.x, p.y, MUSICIAN_SPACING
+            ));
+        }
+    }
+    for a in 0..placements.len() {
+        for b in a + 1..placements.len() {
+            let gap = distance(&placements[a], &placements[b]);
+            if gap < MUSICIAN_SPACING {
+                found.push(format!("Musicians {} and {} are only {} apart", a, b, gap));
+            }
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    // A 100 by 100 stage, so musicians can stand anywhere from 60 to 140
+    fn problem(musicians: usize) -> Problem {
+        Problem {
+            room_width: 200.0,
+            room_height: 200.0,
+            stage_width: 100.0,
+            stage_height: 100.0,
+            stage_bottom_left: vec![50.0, 50.0],
+            musicians: vec![0; musicians],
+            attendees: vec![],
+            pillars: vec![],
+        }
+    }
+
+    fn solution(points: &[(f32, f32)]) -> Solution {
+        Solution {
+            placements: points.iter().map(|&(x, y)| Position { x, y }).collect(),
+        }
+    }
+
+    #[test]
+    fn only_the_overlapping_pair_moves() {
+        let problem = problem(4);
+        let solution = solution(&[(70.0, 70.0), (76.0, 70.0), (100.0, 100.0), (120.0, 120.0)]);
+        let repair = repair(&problem, &solution).unwrap();
+        assert!(violations(&problem, &repair.solution).is_empty());
+        for m in 0..2 {
+            assert!((repair.moved[m] - (MUSICIAN_SPACING + NUDGE_SLACK - 6.0) / 2.0).abs() < 1e-3);
+        }
+        assert_eq!(&repair.moved[2..], &[0.0, 0.0]);
+    }
+
+    #[test]
+    fn crowded_placements_come_out_legal() {
+        let mut rng = StdRng::seed_from_u64(48);
+        let problem = problem(40);
+        for _ in 0..20 {
+            let points: Vec<(f32, f32)> = (0..40)
+                .map(|_| (rng.gen_range(40.0..160.0), rng.gen_range(40.0..160.0)))
+                .collect();
+            let solution = solution(&points);
+            let repair = repair(&problem, &solution).unwrap();
+            assert_eq!(violations(&problem, &repair.solution), Vec::<String>::new());
+
+            // Musicians that were fine to begin with didn't move
+            let (x_min, y_min, x_max, y_max) = usable_area(&problem);
+            for (m, at) in solution.placements.iter().enumerate() {
+                let fine = (x_min..=x_max).contains(&at.x)
+                    && (y_min..=y_max).contains(&at.y)
+                    && solution
+                        .placements
+                        .iter()
+                        .enumerate()
+                        .all(|(k, p)| k == m || distance(p, at) >= MUSICIAN_SPACING);
+                if fine {
+                    assert_eq!(repair.moved[m], 0.0, "musician {}", m);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn no_room_left_starts_over_on_a_packed_stage() {
+        let problem = problem(60);
+        let mut placements = vec![Position { x: 100.0, y: 100.0 }; 60];
+        repair_placements(&problem, &mut placements, &[]).unwrap();
+        assert!(violations(&problem, &Solution { placements }).is_empty());
+    }
+
+    #[test]
+    fn too_many_musicians_is_an_error() {
+        let problem = problem(200);
+        let mut placements = vec![Position { x: 100.0, y: 100.0 }; 200];
+        assert!(repair_placements(&problem, &mut placements, &[]).is_err());
+    }
+
+    #[test]
+    fn missing_musicians_is_an_error() {
+        let problem = problem(3);
+        assert!(repair(&problem, &solution(&[(100.0, 100.0)])).is_err());
+        assert!(repair(&problem, &solution(&[])).is_err());
+    }
+
+    #[test]
+    fn violations_finds_every_kind() {
+        let problem = problem(3);
+        assert!(violations(
+            &problem,
+            &solution(&[(60.0, 60.0), (70.0, 60.0), (140.0, 140.0)])
+        )
+        .is_empty());
+        // Off the stage margin, too close together, and one short
+        let found = violations(&problem, &solution(&[(59.0, 100.0), (100.0, 100.0)]));
+        assert_eq!(found.len(), 2);
+        let found = violations(
+            &problem,
+            &solution(&[(60.0, 60.0), (69.0, 60.0), (100.0, 100.0)]),
+        );
+        assert_eq!(found, vec!["Musicians 0 and 1 are only 9 apart"]);
+    }
+
+    #[test]
+    fn violations_flags_missing_placements() {
+        let problem = problem(3);
+        let found = violations(&problem, &solution(&[(100.0, 100.0)]));
+        assert_eq!(found, vec!["1 placements for 3 musicians"]);
+    }
+}
@@ -1,6 +1,9 @@
 use rapier2d::prelude::*;
 // mod icfp;
+use crate::geometry::*;
 use crate::icfp::*;
+use crate::shadow::*;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 pub(crate) fn add_musician_to_physics(
     rigid_body_set: &mut RigidBodySet,
@@ -139,7 +142,82 @@ pub fn player_scorer(
     score
 }
 
-pub fn scorer(problem: &Problem, solution: &Solution) -> f32 {
+// What a musician playing `instrument` at `player` is worth to an attendee
+// who can hear them
+pub fn impact(attendee: &Attendee, instrument: i64, player: &Position) -> f32 {
+    let distance_squared = (player.x - attendee.x).powi(2) + (player.y - attendee.y).powi(2);
+    ((attendee.tastes[instrument as usize] * 1000000.0) / distance_squared).ceil()
+}
+
+// The v2 "playing together" factor for each musician: their impact is
+// multiplied by 1 plus the sum of 1/distance to every other musician playing
+// the same instrument
+pub fn closeness(problem: &Problem, placements: &[Position]) -> Vec<f64> {
+    placements
+        .iter()
+        .enumerate()
+        .map(|(m, at)| {
+            1.0 + placements
+                .iter()
+                .enumerate()
+                .filter(|&(k, _)| k != m && problem.musicians[k] == problem.musicians[m])
+                .map(|(_, other)| 1.0 / distance(at, other) as f64)
+                .sum::<f64>()
+        })
+        .collect()
+}
+
+// Which attendees have a clear line of sight from each of the `from` points,
+// past the musicians at `placements` and the pillars. A musician standing
+// right on a point doesn't get in its own way. This works geometrically,
+// which is much quicker than asking rapier one ray at a time
+pub fn lines_of_sight(
+    problem: &Problem,
+    from: &[Position],
+    placements: &[Position],
+) -> Vec<Vec<bool>> {
+    let attendees: Vec<Position> = problem
+        .attendees
+        .iter()
+        .map(|a| Position { x: a.x, y: a.y })
+        .collect();
+
+    let shadows = ShadowMap::new(problem);
+
+    from.iter()
+        .map(|point| {
+            let around = sorted_by_angle(point, &attendees);
+            let mut clear: Vec<bool> = (0..attendees.len())
+                .map(|a| shadows.is_visible(a, point))
+                .collect();
+            for center in placements {
+                if center == point {
+                    continue;
+                }
+                in_window(
+                    &around,
+                    angle(point, center),
+                    window_width(5.0, distance(point, center)),
+                    |a| {
+                        if is_blocked(point, &attendees[a], center, 5.0) {
+                            clear[a] = false;
+                        }
+                    },
+                );
+            }
+            clear
+        })
+        .collect()
+}
+
+// Which attendees can hear each musician
+pub fn audible(problem: &Problem, placements: &Placements) -> Vec<Vec<bool>> {
+    lines_of_sight(problem, placements, placements)
+}
+
+// Happiness of each attendee, in problem order, ignoring whether the
+// placement is legal
+pub fn attendee_scores(problem: &Problem, solution: &Solution) -> Vec<f32> {
     let mut rigid_body_set = RigidBodySet::new();
     let mut collider_set = ColliderSet::new();
 
@@ -172,10 +250,11 @@ pub fn scorer(problem: &Problem, solution: &Solution) -> f32 {
     query_pipeline.update(&rigid_body_set, &collider_set);
 
     // loop over attendees and trace a line to each placement if it intersects a pillar, or another placement, then 0, otherwise take the distance of the line
-    let mut score = 0.0;
+    let mut scores = Vec::new();
 
     for attendee in &problem.attendees {
         // eprintln!("Working on attendee");
+        let mut score = 0.0;
         for (i, player) in solution.placements.clone().iter().enumerate() {
             // eprintln!("Working on musician {} at ({},{})", i, player.x, player.y);
 
@@ -233,8 +312,15 @@ pub fn scorer(problem: &Problem, solution: &Solution) -> f32 {
                 score += player_score;
             }
         }
+        scores.push(score);
     }
 
+    scores
+}
+
+pub fn scorer(problem: &Problem, solution: &Solution) -> f32 {
+    let mut score: f32 = attendee_scores(problem, solution).iter().sum();
+
     let player_physical_presence = 10.0;
 
     // validate that all players are on the stage with the stage_bottom_left and stage_width/height
@@ -257,3 +343,23 @@ pub fn scorer(problem: &Problem, solution: &Solution) -> f32 {
 
     score
 }
+
+// Every solver logs its progress its own way, so whenever one has a complete
+// solution scoring this much it also says so in one agreed form, for
+// anything watching (like the portfolio runner) to pick up
+pub fn report(score: f64) {
+    if !QUIET.load(Ordering::Relaxed) {
+        eprintln!("score {}", score);
+    }
+}
+
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+// Do some work without reporting any scores, for when it's only solving part
+// of the problem and its scores would mislead whoever is watching
+pub fn quietly<T>(work: impl FnOnce() -> T) -> T {
+    QUIET.store(true, Ordering::Relaxed);
+    let result = work();
+    QUIET.store(false, Ordering::Relaxed);
+    result
+}
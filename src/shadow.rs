@@ -0,0 +1,225 @@
+use crate::geometry::*;
+use crate::icfp::*;
+use std::f32::consts::PI;
+
+// Pad each shadow a little so rounding never lets a blocked line of sight
+// slip past. The exact check afterwards sorts out the edges
+const SHADOW_PADDING: f32 = 0.00001;
+
+// The pillars in the way in each direction from one attendee. The circle of
+// directions is cut up wherever a pillar's shadow starts or ends, and each
+// piece lists the pillars that cover it
+#[derive(Debug, Clone, Default)]
+struct Shadows {
+    starts: Vec<f32>,
+    covering: Vec<Vec<u32>>,
+}
+
+// Which stage points each attendee can't see because of pillars. Built once
+// per problem, after which each lookup is a binary search plus an exact check
+// against the (usually zero or one) pillars in that direction
+#[derive(Debug, Clone)]
+pub struct ShadowMap {
+    attendees: Vec<Position>,
+    pillars: Vec<(Position, f32)>,
+    shadows: Vec<Shadows>,
+}
+
+impl ShadowMap {
+    pub fn new(problem: &Problem) -> ShadowMap {
+        let attendees: Vec<Position> = problem
+            .attendees
+            .iter()
+            .map(|a| Position { x: a.x, y: a.y })
+            .collect();
+        let pillars: Vec<(Position, f32)> = problem
+            .pillars
+            .iter()
+            .map(|p| {
+                let center = Position {
+                    x: p.center[0],
+                    y: p.center[1],
+                };
+                (center, p.radius)
+            })
+            .collect();
+        let shadows = attendees
+            .iter()
+            .map(|attendee| shadows_from(attendee, &pillars))
+            .collect();
+
+        ShadowMap {
+            attendees,
+            pillars,
+            shadows,
+        }
+    }
+
+    // Whether attendee `attendee` has a clear line to `from`, as far as the
+    // pillars go
+    pub fn is_visible(&self, attendee: usize, from: &Position) -> bool {
+        if self.pillars.is_empty() {
+            return true;
+        }
+        let at = &self.attendees[attendee];
+        let shadows = &self.shadows[attendee];
+        let mut direction = angle(at, from);
+        if direction >= PI {
+            direction -= 2.0 * PI;
+        }
+        let piece = shadows.starts.partition_point(|&start| start <= direction);
+        if piece == 0 {
+            return true;
+        }
+        shadows.covering[piece - 1].iter().all(|&p| {
+            let (center, radius) = &self.pillars[p as usize];
+            !is_blocked(at, from, center, *radius)
+        })
+    }
+}
+
+fn shadows_from(attendee: &Position, pillars: &[(Position, f32)]) -> Shadows {
+    // Every pillar casts a wedge of shadow; wedges that wrap past PI are
+    // split in two
+    let mut wedges: Vec<(f32, f32, u32)> = Vec::new();
+    for (p, (center, radius)) in pillars.iter().enumerate() {
+        let direction = angle(attendee, center);
+        let width = window_width(*radius, distance(attendee, center)) + SHADOW_PADDING;
+        let (from, to) = (direction - width, direction + width);
+        if width >= PI {
+            wedges.push((-PI, PI, p as u32));
+        } else if from < -PI {
+            wedges.push((from + 2.0 * PI, PI, p as u32));
+            wedges.push((-PI, to, p as u32));
+        } else if to > PI {
+            wedges.push((from, PI, p as u32));
+            wedges.push((-PI, to - 2.0 * PI, p as u32));
+        } else {
+            wedges.push((from, to, p as u32));
+        }
+    }
+
+    // Sweep around the circle, starting a new piece at every wedge edge
+    let mut events: Vec<(f32, bool, u32)> = Vec::new();
+    for &(from, to, p) in &wedges {
+        events.push((from, true, p));
+        events.push((to, false, p));
+    }
+    events.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+
+    let mut shadows = Shadows::default();
+    let mut active: Vec<u32> = Vec::new();
+    for (at, starts, p) in events {
+        if starts {
+            active.push(p);
+        } else if let Some(i) = active.iter().position(|&q| q == p) {
+            active.swap_remove(i);
+        }
+        if shadows.starts.last() == Some(&at) {
+            *shadows.covering.last_mut().unwrap() = active.clone();
+        } else {
+            shadows.starts.push(at);
+            shadows.covering.push(active.clone());
+        }
+    }
+    shadows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    fn brute_force(problem: &Problem, attendee: usize, from: &Position) -> bool {
+        let at = Position {
+            x: problem.attendees[attendee].x,
+            y: problem.attendees[attendee].y,
+        };
+        problem.pillars.iter().all(|p| {
+            let center = Position {
+                x: p.center[0],
+                y: p.center[1],
+            };
+            !is_blocked(&at, from, &center, p.radius)
+        })
+    }
+
+    #[test]
+    fn matches_checking_every_pillar() {
+        let mut rng = StdRng::seed_from_u64(32);
+        for _ in 0..20 {
+            let mut problem = Problem::default();
+            for _ in 0..rng.gen_range(0..30) {
+                problem.pillars.push(Pillar {
+                    center: vec![rng.gen_range(0.0..200.0), rng.gen_range(0.0..200.0)],
+                    radius: rng.gen_range(1.0..30.0),
+                });
+            }
+            for _ in 0..50 {
+                problem.attendees.push(Attendee {
+                    x: rng.gen_range(0.0..200.0),
+                    y: rng.gen_range(0.0..200.0),
+                    tastes: vec![],
+                });
+            }
+            let shadows = ShadowMap::new(&problem);
+
+            for _ in 0..200 {
+                let from = Position {
+                    x: rng.gen_range(0.0..200.0),
+                    y: rng.gen_range(0.0..200.0),
+                };
+                for a in 0..problem.attendees.len() {
+                    assert_eq!(
+                        shadows.is_visible(a, &from),
+                        brute_force(&problem, a, &from),
+                        "attendee {} looking at {:?}",
+                        a,
+                        from
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn shadows_wrapping_past_pi() {
+        // Pillars straight behind, just above and just below the line where
+        // angles wrap round
+        let problem = Problem {
+            attendees: vec![Attendee {
+                x: 100.0,
+                y: 100.0,
+                tastes: vec![],
+            }],
+            pillars: vec![
+                Pillar {
+                    center: vec![50.0, 100.0],
+                    radius: 5.0,
+                },
+                Pillar {
+                    center: vec![50.0, 101.0],
+                    radius: 2.0,
+                },
+                Pillar {
+                    center: vec![50.0, 99.0],
+                    radius: 2.0,
+                },
+            ],
+            ..Problem::default()
+        };
+        let shadows = ShadowMap::new(&problem);
+        for y in [90.0, 95.0, 97.0, 99.5, 100.0, 100.5, 103.0, 105.0, 110.0] {
+            let from = Position { x: 0.0, y };
+            assert_eq!(
+                shadows.is_visible(0, &from),
+                brute_force(&problem, 0, &from),
+                "looking at {:?}",
+                from
+            );
+        }
+        assert!(!shadows.is_visible(0, &Position { x: 0.0, y: 100.0 }));
+        assert!(shadows.is_visible(0, &Position { x: 200.0, y: 100.0 }));
+    }
+}
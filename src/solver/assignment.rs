@@ -0,0 +1,216 @@
+use crate::icfp::*;
+use crate::scorer::*;
+
+// Minimum cost assignment of rows to columns of a square matrix (the
+// Hungarian algorithm, with potentials). Returns the column for each row
+pub(crate) fn hungarian(cost: &[Vec<f64>]) -> Vec<usize> {
+    let n = cost.len();
+    // 1-based, with row/column 0 as the "unassigned" sentinel
+    let mut u = vec![0.0; n + 1];
+    let mut v = vec![0.0; n + 1];
+    let mut row_of = vec![0; n + 1];
+    let mut way = vec![0; n + 1];
+
+    for row in 1..=n {
+        row_of[0] = row;
+        let mut col0 = 0;
+        let mut min_v = vec![f64::INFINITY; n + 1];
+        let mut used = vec![false; n + 1];
+        loop {
+            used[col0] = true;
+            let row0 = row_of[col0];
+            let mut delta = f64::INFINITY;
+            let mut col1 = 0;
+            for col in 1..=n {
+                if used[col] {
+                    continue;
+                }
+                let reduced = cost[row0 - 1][col - 1] - u[row0] - v[col];
+                if reduced < min_v[col] {
+                    min_v[col] = reduced;
+                    way[col] = col0;
+                }
+                if min_v[col] < delta {
+                    delta = min_v[col];
+                    col1 = col;
+                }
+            }
+            for col in 0..=n {
+                if used[col] {
+                    u[row_of[col]] += delta;
+                    v[col] -= delta;
+                } else {
+                    min_v[col] -= delta;
+                }
+            }
+            col0 = col1;
+            if row_of[col0] == 0 {
+                break;
+            }
+        }
+        loop {
+            let col1 = way[col0];
+            row_of[col0] = row_of[col1];
+            col0 = col1;
+            if col0 == 0 {
+                break;
+            }
+        }
+    }
+
+    let mut col_of = vec![0; n];
+    for col in 1..=n {
+        col_of[row_of[col] - 1] = col - 1;
+    }
+    col_of
+}
+
+// Put each musician at whichever of the current positions suits them best.
+// Moving musicians between occupied positions doesn't change who blocks whom,
+// so each position's audience is fixed and this is a plain assignment problem
+fn assign_once(problem: &Problem, solution: &Solution) -> Solution {
+    let positions = &solution.placements;
+    let hears = audible(problem, positions);
+
+    let mut instruments = problem.musicians.clone();
+    instruments.sort();
+    instruments.dedup();
+
+    // Musicians with the same instrument are worth the same anywhere, so only
+    // work out one value per instrument and position
+    let values: Vec<Vec<f64>> = instruments
+        .iter()
+        .map(|&instrument| {
+            positions
+                .iter()
+                .zip(&hears)
+                .map(|(position, hears)| {
+                    problem
+                        .attendees
+                        .iter()
+                        .zip(hears)
+                        .filter(|(_, &hears)| hears)
+                        .map(|(attendee, _)| impact(attendee, instrument, position) as f64)
+                        .sum()
+                })
+                .collect()
+        })
+        .collect();
+
+    let cost: Vec<Vec<f64>> = problem
+        .musicians
+        .iter()
+        .map(|instrument| {
+            let i = instruments.binary_search(instrument).unwrap();
+            values[i].iter().map(|value| -value).collect()
+        })
+        .collect();
+
+    let placements = hungarian(&cost)
+        .into_iter()
+        .map(|position| positions[position])
+        .collect();
+    Solution { placements }
+}
+
+// Keep reassigning musicians to the occupied positions until the score stops
+// going up. Works as a post-pass after any solver
+pub fn improve(problem: &Problem, solution: &Solution) -> Solution {
+    let mut best_solution = solution.clone();
+    let mut best_score = scorer(problem, &best_solution);
+    eprintln!("Assignment starting from {}", best_score);
+
+    loop {
+        let solution = assign_once(problem, &best_solution);
+        let score = scorer(problem, &solution);
+        eprintln!("Assignment pass scored {}", score);
+        if score <= best_score {
+            break;
+        }
+        best_score = score;
+        best_solution = solution;
+    }
+
+    best_solution
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    // Cheapest total over every permutation, for checking small matrices
+    fn brute_force(cost: &[Vec<f64>]) -> f64 {
+        fn go(cost: &[Vec<f64>], row: usize, used: &mut Vec<bool>) -> f64 {
+            if row == cost.len() {
+                return 0.0;
+            }
+            let mut best = f64::INFINITY;
+            for col in 0..cost.len() {
+                if !used[col] {
+                    used[col] = true;
+                    best = best.min(cost[row][col] + go(cost, row + 1, used));
+                    used[col] = false;
+                }
+            }
+            best
+        }
+        go(cost, 0, &mut vec![false; cost.len()])
+    }
+
+    #[test]
+    fn hungarian_matches_brute_force() {
+        let mut rng = StdRng::seed_from_u64(28);
+        for n in 1..=7 {
+            for _ in 0..20 {
+                let cost: Vec<Vec<f64>> = (0..n)
+                    .map(|_| (0..n).map(|_| rng.gen_range(-100.0..100.0)).collect())
+                    .collect();
+                let cols = hungarian(&cost);
+
+                let mut seen = cols.clone();
+                seen.sort();
+                assert_eq!(seen, (0..n).collect::<Vec<_>>());
+                let total: f64 = cols.iter().enumerate().map(|(r, &c)| cost[r][c]).sum();
+                assert!((total - brute_force(&cost)).abs() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn improve_never_scores_worse() {
+        let problem = Problem {
+            room_width: 100.0,
+            room_height: 100.0,
+            stage_width: 50.0,
+            stage_height: 50.0,
+            stage_bottom_left: vec![25.0, 25.0],
+            musicians: vec![0, 1, 0],
+            attendees: vec![
+                Attendee {
+                    x: 10.0,
+                    y: 50.0,
+                    tastes: vec![1000.0, -1000.0],
+                },
+                Attendee {
+                    x: 90.0,
+                    y: 50.0,
+                    tastes: vec![-1000.0, 1000.0],
+                },
+            ],
+            pillars: vec![],
+        };
+        let solution = Solution {
+            placements: vec![
+                Position { x: 35.0, y: 35.0 },
+                Position { x: 35.0, y: 65.0 },
+                Position { x: 65.0, y: 50.0 },
+            ],
+        };
+        let improved = improve(&problem, &solution);
+        assert!(scorer(&problem, &improved) >= scorer(&problem, &solution));
+        // Instrument 1 is liked on the right, so it ends up there
+        assert_eq!(improved.placements[1], Position { x: 65.0, y: 50.0 });
+    }
+}
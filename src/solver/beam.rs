@@ -0,0 +1,384 @@
+use crate::candidates::*;
+use crate::deadline;
+use crate::geometry::*;
+use crate::icfp::*;
+use crate::params::*;
+use crate::scorer::*;
+use crate::shadow::*;
+use crate::symmetry::*;
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+// Every point keeps track of who it can see and is ranked for every
+// instrument, so fewer than most solvers
+const BEAM_CANDIDATES: usize = 1000;
+const TIME_BUDGET: Duration = Duration::from_secs(120);
+
+pub const PARAMS: &[Param] = &[
+    Param {
+        name: "width",
+        min: 1.0,
+        max: 50.0,
+        default: 10.0,
+        integer: true,
+    },
+    Param {
+        name: "expansions",
+        min: 1.0,
+        max: 50.0,
+        default: 10.0,
+        integer: true,
+    },
+];
+
+#[derive(Debug, Clone)]
+pub struct Settings {
+    // How many partial placements to keep after each musician
+    pub width: usize,
+    // How many points to try for the next musician from each of them
+    pub expansions: usize,
+    // Once this runs out, whoever is left is placed greedily
+    pub time_budget: Duration,
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings::from_params(&Params::defaults(PARAMS))
+    }
+}
+
+impl Settings {
+    pub fn from_params(params: &Params) -> Settings {
+        Settings {
+            width: params.count("width").max(1),
+            expansions: params.count("expansions").max(1),
+            time_budget: TIME_BUDGET,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct State {
+    // Point for each musician placed so far, in placing order
+    placed: Vec<usize>,
+    // Which of the attendees that can see each musician placed so far still
+    // hear them past everyone else, in the same order as `visible`
+    heard: Vec<Vec<bool>>,
+    score: f64,
+}
+
+struct Beam<'a> {
+    problem: &'a Problem,
+    points: Vec<Position>,
+    attendees: Vec<Position>,
+    // Attendees that can see each point past the pillars
+    visible: Vec<Vec<usize>>,
+    // Musicians in the order they're placed
+    order: Vec<usize>,
+    // Points for each instrument, best first, ignoring everyone else
+    ranked: Vec<Vec<usize>>,
+    instruments: Vec<i64>,
+}
+
+impl<'a> Beam<'a> {
+    fn instrument(&self, k: usize) -> i64 {
+        self.problem.musicians[self.order[k]]
+    }
+
+    // Whether a musician already placed is in the way from point `from`
+    fn blocked(&self, placed: &[usize], from: usize, attendee: usize) -> bool {
+        placed.iter().any(|&q| {
+            q != from
+                && is_blocked(
+                    &self.points[from],
+                    &self.attendees[attendee],
+                    &self.points[q],
+                    MUSICIAN_RADIUS,
+                )
+        })
+    }
+
+    // How much the score changes if the next musician stands at `p`: what
+    // they add for everyone they can reach, less whatever they take away
+    // from musicians already placed by getting in the way
+    fn gain(&self, state: &State, p: usize) -> f64 {
+        let instrument = self.instrument(state.placed.len());
+        let at = &self.points[p];
+        let mut gain = 0.0;
+        for &a in &self.visible[p] {
+            if !self.blocked(&state.placed, p, a) {
+                gain += impact(&self.problem.attendees[a], instrument, at) as f64;
+            }
+        }
+        for (j, (&q, heard)) in state.placed.iter().zip(&state.heard).enumerate() {
+            let from = &self.points[q];
+            for (&a, &heard) in self.visible[q].iter().zip(heard) {
+                if heard && is_blocked(from, &self.attendees[a], at, MUSICIAN_RADIUS) {
+                    gain -= impact(&self.problem.attendees[a], self.instrument(j), from) as f64;
+                }
+            }
+        }
+        gain
+    }
+
+    // `state` with the next musician at `p`, which scores `score`
+    fn place(&self, state: &State, p: usize, score: f64) -> State {
+        let at = &self.points[p];
+        let mut heard: Vec<Vec<bool>> = state
+            .placed
+            .iter()
+            .zip(&state.heard)
+            .map(|(&q, heard)| {
+                self.visible[q]
+                    .iter()
+                    .zip(heard)
+                    .map(|(&a, &heard)| {
+                        heard
+                            && !is_blocked(&self.points[q], &self.attendees[a], at, MUSICIAN_RADIUS)
+                    })
+                    .collect()
+            })
+            .collect();
+        heard.push(
+            self.visible[p]
+                .iter()
+                .map(|&a| !self.blocked(&state.placed, p, a))
+                .collect(),
+        );
+        let mut placed = state.placed.clone();
+        placed.push(p);
+        State {
+            placed,
+            heard,
+            score,
+        }
+    }
+
+    fn fits(&self, placed: &[usize], p: usize) -> bool {
+        placed
+            .iter()
+            .all(|&q| distance(&self.points[q], &self.points[p]) >= MUSICIAN_SPACING)
+    }
+
+    // The same musicians at the same points, up to swapping musicians who
+    // play the same instrument
+    fn key(&self, placed: &[usize]) -> Vec<(i64, usize)> {
+        let mut key: Vec<(i64, usize)> = placed
+            .iter()
+            .enumerate()
+            .map(|(k, &p)| (self.instrument(k), p))
+            .collect();
+        key.sort();
+        key
+    }
+
+    // The best points for the next musician after `state`, and what `state`
+    // would score with them there
+    fn expand(&self, state: &State, expansions: usize) -> Vec<(usize, f64)> {
+        let instrument = self.instrument(state.placed.len());
+        let k = self.instruments.binary_search(&instrument).unwrap();
+        self.ranked[k]
+            .iter()
+            .filter(|&&p| self.fits(&state.placed, p))
+            .take(expansions)
+            .map(|&p| (p, state.score + self.gain(state, p)))
+            .collect()
+    }
+
+    // Place everyone left after `state` one at a time, each at whichever of
+    // their best few free points reaches the most attendees. Which
+    // attendees every point can still reach is kept up to date as musicians
+    // are placed, instead of checking everyone placed for every point, so
+    // this is quick even on huge stages. None if anyone doesn't fit
+    fn finish(&self, state: &State, expansions: usize) -> Option<Vec<usize>> {
+        let mut placed = state.placed.clone();
+        let mut clear: Vec<Vec<bool>> = self
+            .visible
+            .iter()
+            .enumerate()
+            .map(|(p, visible)| {
+                visible
+                    .iter()
+                    .map(|&a| !self.blocked(&placed, p, a))
+                    .collect()
+            })
+            .collect();
+
+        while placed.len() < self.order.len() {
+            let instrument = self.instrument(placed.len());
+            let k = self.instruments.binary_search(&instrument).unwrap();
+            let value = |p: usize| -> f64 {
+                self.visible[p]
+                    .iter()
+                    .zip(&clear[p])
+                    .filter(|(_, &clear)| clear)
+                    .map(|(&a, _)| {
+                        impact(&self.problem.attendees[a], instrument, &self.points[p]) as f64
+                    })
+                    .sum()
+            };
+            let next = self.ranked[k]
+                .iter()
+                .copied()
+                .filter(|&p| self.fits(&placed, p))
+                .take(expansions)
+                .max_by(|&a, &b| value(a).total_cmp(&value(b)))?;
+
+            let at = &self.points[next];
+            for (p, clear) in clear.iter_mut().enumerate() {
+                if p == next {
+                    continue;
+                }
+                for (&a, clear) in self.visible[p].iter().zip(clear.iter_mut()) {
+                    if *clear
+                        && is_blocked(&self.points[p], &self.attendees[a], at, MUSICIAN_RADIUS)
+                    {
+                        *clear = false;
+                    }
+                }
+            }
+            placed.push(next);
+        }
+        Some(placed)
+    }
+}
+
+// Beam search: place musicians one at a time like greedy does, but keep the
+// best `width` partial placements at every step instead of committing to
+// one. The most valuable instruments go first, and partial placements that
+// only differ by which same-instrument musician stands where are only kept
+// once. If the time budget runs out, the best partial placement so far is
+// finished off greedily
+pub fn solve_with(problem: &Problem, settings: &Settings) -> Solution {
+    let start = Instant::now();
+    let points = candidates(problem, BEAM_CANDIDATES);
+    let attendees: Vec<Position> = problem
+        .attendees
+        .iter()
+        .map(|a| Position { x: a.x, y: a.y })
+        .collect();
+    let shadows = ShadowMap::new(problem);
+    let visible: Vec<Vec<usize>> = points
+        .iter()
+        .map(|point| {
+            (0..attendees.len())
+                .filter(|&a| shadows.is_visible(a, point))
+                .collect()
+        })
+        .collect();
+
+    let groups = groups(problem);
+    let instruments: Vec<i64> = groups.iter().map(|g| problem.musicians[g[0]]).collect();
+    let mut ranked = Vec::new();
+    let mut importance = Vec::new();
+    for &instrument in &instruments {
+        let mut values: Vec<(f64, usize)> = points
+            .iter()
+            .enumerate()
+            .map(|(p, point)| {
+                let value: f64 = visible[p]
+                    .iter()
+                    .map(|&a| impact(&problem.attendees[a], instrument, point) as f64)
+                    .sum();
+                (value, p)
+            })
+            .collect();
+        values.sort_by(|a, b| b.0.total_cmp(&a.0));
+        importance.push(values.first().map_or(0.0, |v| v.0));
+        ranked.push(values.into_iter().map(|(_, p)| p).collect());
+    }
+
+    let mut by_importance: Vec<usize> = (0..groups.len()).collect();
+    by_importance.sort_by(|&a, &b| importance[b].total_cmp(&importance[a]));
+    let order: Vec<usize> = by_importance
+        .iter()
+        .flat_map(|&k| groups[k].iter().copied())
+        .collect();
+
+    let search = Beam {
+        problem,
+        points,
+        attendees,
+        visible,
+        order,
+        ranked,
+        instruments,
+    };
+
+    let deadline = start + deadline::budget(settings.time_budget);
+    let mut beam = vec![State {
+        placed: Vec::new(),
+        heard: Vec::new(),
+        score: 0.0,
+    }];
+    let mut out_of_time = false;
+    while beam[0].placed.len() < search.order.len() {
+        if Instant::now() > deadline {
+            out_of_time = true;
+            break;
+        }
+
+        // Which state each child comes from, where the next musician goes,
+        // and what it scores
+        let mut children: Vec<(usize, usize, f64)> = beam
+            .iter()
+            .enumerate()
+            .flat_map(|(s, state)| {
+                search
+                    .expand(state, settings.expansions)
+                    .into_iter()
+                    .map(move |(p, score)| (s, p, score))
+            })
+            .collect();
+        children.sort_by(|a, b| b.2.total_cmp(&a.2));
+        let mut seen = HashSet::new();
+        children.retain(|&(s, p, _)| {
+            let mut placed = beam[s].placed.clone();
+            placed.push(p);
+            seen.insert(search.key(&placed))
+        });
+        children.truncate(settings.width);
+        if children.is_empty() {
+            eprintln!("Beam search ran out of room, using greedy instead");
+            return crate::solver::greedy::solve(problem);
+        }
+        beam = children
+            .iter()
+            .map(|&(s, p, score)| search.place(&beam[s], p, score))
+            .collect();
+        eprintln!("Placed {}, best {}", beam[0].placed.len(), beam[0].score);
+    }
+
+    let placed = if out_of_time {
+        eprintln!(
+            "Out of time after placing {}, finishing greedily",
+            beam[0].placed.len()
+        );
+        let Some(placed) = search.finish(&beam[0], settings.expansions) else {
+            eprintln!("Beam search ran out of room, using greedy instead");
+            return crate::solver::greedy::solve(problem);
+        };
+        placed
+    } else {
+        eprintln!("Beam search best {}", beam[0].score);
+        beam[0].placed.clone()
+    };
+
+    let mut placements = vec![Position { x: 0.0, y: 0.0 }; problem.musicians.len()];
+    for (&m, &p) in search.order.iter().zip(&placed) {
+        placements[m] = search.points[p];
+    }
+    let solution = Solution { placements };
+    if out_of_time {
+        eprintln!("Beam search scored {}", scorer(problem, &solution));
+    }
+    solution
+}
+
+pub fn solve_with_params(problem: &Problem, params: &Params) -> Solution {
+    solve_with(problem, &Settings::from_params(params))
+}
+
+pub fn solve(problem: &Problem) -> Solution {
+    solve_with(problem, &Settings::default())
+}
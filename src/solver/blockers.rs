@@ -0,0 +1,64 @@
+use crate::candidates::*;
+use crate::delta::*;
+use crate::icfp::*;
+use crate::scorer::*;
+
+// Where to look for hiding spots
+const HIDING_SPOTS: usize = 1000;
+// Only try to hide the worst few musicians each pass, to keep big problems
+// quick
+const MAX_MUSICIANS_PER_PASS: usize = 50;
+// Scores are whole numbers, so anything less is rounding noise
+const MIN_GAIN: f64 = 1.0;
+
+// Musicians the audience dislikes overall, worst first
+fn negative_musicians(delta: &DeltaScorer) -> Vec<usize> {
+    let mut negative: Vec<(usize, f64)> = (0..delta.placements().len())
+        .map(|m| (m, delta.contribution(m)))
+        .filter(|(_, contribution)| *contribution < 0.0)
+        .collect();
+    negative.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+    negative.into_iter().map(|(m, _)| m).collect()
+}
+
+// Move musicians that do more harm than good to wherever they lose the least.
+// Usually that's tucked behind other musicians, out of sight of the people
+// who dislike them, or somewhere they block someone else's unpopular sound.
+// Works as a post-pass after any solver
+pub fn improve(problem: &Problem, solution: &Solution) -> Solution {
+    let points = candidates(problem, HIDING_SPOTS);
+    let mut delta = DeltaScorer::new(problem, solution);
+    eprintln!("Blockers starting from {}", scorer(problem, solution));
+
+    loop {
+        let negative = negative_musicians(&delta);
+        eprintln!("{} musicians have a negative contribution", negative.len());
+
+        let mut moved = 0;
+        for &m in negative.iter().take(MAX_MUSICIANS_PER_PASS) {
+            let mut best: Option<(Position, f64)> = None;
+            for point in &points {
+                if !delta.can_move(m, point) {
+                    continue;
+                }
+                let change = delta.move_delta(m, point);
+                if change > best.map_or(MIN_GAIN, |(_, best)| best) {
+                    best = Some((*point, change));
+                }
+            }
+            if let Some((point, change)) = best {
+                delta.move_to(m, &point);
+                moved += 1;
+                eprintln!("Moved musician {} for {}, now {}", m, change, delta.score());
+            }
+        }
+
+        if moved == 0 {
+            break;
+        }
+    }
+
+    let solution = delta.solution();
+    eprintln!("Blockers scored {}", scorer(problem, &solution));
+    solution
+}
@@ -0,0 +1,319 @@
+use crate::candidates::*;
+use crate::delta::*;
+use crate::geometry::*;
+use crate::icfp::*;
+use crate::scorer::*;
+use crate::shadow::*;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+// How many of the best centers to grow each cluster from before picking one
+const SEEDS: usize = 5;
+
+// The stage as a hexagonal packing at minimum spacing. Clusters are grown on
+// it point by point, so any two musicians, in the same cluster or not, are
+// always far enough apart
+struct Packing<'a> {
+    problem: &'a Problem,
+    shadows: ShadowMap,
+    points: Vec<Position>,
+    // Points by which MUSICIAN_SPACING square they fall in
+    cells: HashMap<(i32, i32), Vec<usize>>,
+    taken: Vec<bool>,
+    // What an instrument would be worth at a point, ignoring other musicians
+    values: HashMap<(i64, usize), f64>,
+}
+
+impl<'a> Packing<'a> {
+    fn new(problem: &'a Problem) -> Packing<'a> {
+        let points = hexagonal(problem, MUSICIAN_SPACING);
+        let mut cells: HashMap<(i32, i32), Vec<usize>> = HashMap::new();
+        for (i, point) in points.iter().enumerate() {
+            cells.entry(cell(point)).or_default().push(i);
+        }
+        Packing {
+            problem,
+            shadows: ShadowMap::new(problem),
+            taken: vec![false; points.len()],
+            points,
+            cells,
+            values: HashMap::new(),
+        }
+    }
+
+    // Points within `radius` of `around`
+    fn nearby(&self, around: &Position, radius: f32) -> Vec<usize> {
+        let (cx, cy) = cell(around);
+        let reach = (radius / MUSICIAN_SPACING).ceil() as i32;
+        let mut found = Vec::new();
+        for x in cx - reach..=cx + reach {
+            for y in cy - reach..=cy + reach {
+                if let Some(points) = self.cells.get(&(x, y)) {
+                    found.extend(
+                        points
+                            .iter()
+                            .filter(|&&i| distance(&self.points[i], around) <= radius),
+                    );
+                }
+            }
+        }
+        found
+    }
+
+    // The six points around a point in the packing
+    fn neighbors(&self, i: usize) -> Vec<usize> {
+        let mut neighbors = self.nearby(&self.points[i], MUSICIAN_SPACING * 1.01);
+        neighbors.retain(|&j| j != i);
+        neighbors
+    }
+
+    fn nearest(&self, around: &Position) -> usize {
+        let mut radius = MUSICIAN_SPACING;
+        loop {
+            let closest = self.nearby(around, radius).into_iter().min_by(|&a, &b| {
+                distance(&self.points[a], around).total_cmp(&distance(&self.points[b], around))
+            });
+            if let Some(i) = closest {
+                return i;
+            }
+            radius *= 2.0;
+        }
+    }
+
+    fn value(&mut self, instrument: i64, i: usize) -> f64 {
+        if let Some(value) = self.values.get(&(instrument, i)) {
+            return *value;
+        }
+        let point = self.points[i];
+        let value = self
+            .problem
+            .attendees
+            .iter()
+            .enumerate()
+            .filter(|(a, _)| self.shadows.is_visible(*a, &point))
+            .map(|(_, attendee)| impact(attendee, instrument, &point) as f64)
+            .sum();
+        self.values.insert((instrument, i), value);
+        value
+    }
+
+    // Some free point, as close to `around` as possible, for when a cluster
+    // has boxed itself in. Points in `growing` are already in the cluster
+    // but not taken yet. None once the stage is full
+    fn free_near(&self, around: &Position, growing: &HashSet<usize>) -> Option<usize> {
+        let (x_min, y_min, x_max, y_max) = usable_area(self.problem);
+        let furthest = (x_max - x_min).hypot(y_max - y_min) + MUSICIAN_SPACING;
+        let mut radius = MUSICIAN_SPACING * 2.0;
+        loop {
+            let free = self
+                .nearby(around, radius)
+                .into_iter()
+                .filter(|i| !self.taken[*i] && !growing.contains(i))
+                .min_by(|&a, &b| {
+                    distance(&self.points[a], around).total_cmp(&distance(&self.points[b], around))
+                });
+            match free {
+                Some(i) => return Some(i),
+                None if radius > furthest => return None,
+                None => radius *= 2.0,
+            }
+        }
+    }
+}
+
+fn cell(point: &Position) -> (i32, i32) {
+    (
+        (point.x / MUSICIAN_SPACING).floor() as i32,
+        (point.y / MUSICIAN_SPACING).floor() as i32,
+    )
+}
+
+// Grow a cluster of `size` points for `instrument` out from `seed`, each time
+// adding whichever free neighboring point adds most, counting the closeness
+// bonus it gives and gets from the rest of the cluster. The cluster stretches
+// towards its fans rather than staying round. None if the stage fills up
+// first
+fn grow(packing: &mut Packing, instrument: i64, seed: usize, size: usize) -> Option<Vec<usize>> {
+    let mut cluster = vec![seed];
+    let mut in_cluster = HashSet::from([seed]);
+    while cluster.len() < size {
+        let mut frontier: Vec<usize> = cluster
+            .iter()
+            .flat_map(|&i| packing.neighbors(i))
+            .filter(|i| !packing.taken[*i] && !in_cluster.contains(i))
+            .collect();
+        frontier.sort();
+        frontier.dedup();
+        if frontier.is_empty() {
+            frontier.push(packing.free_near(&packing.points[seed], &in_cluster)?);
+        }
+
+        let mut best: Option<(usize, f64)> = None;
+        for &f in &frontier {
+            let mut gain = packing.value(instrument, f);
+            for &i in &cluster {
+                let d = distance(&packing.points[f], &packing.points[i]) as f64;
+                gain += (packing.value(instrument, f) + packing.value(instrument, i)) / d;
+            }
+            let better = match best {
+                Some((_, best)) => gain > best,
+                None => true,
+            };
+            if better {
+                best = Some((f, gain));
+            }
+        }
+        let (chosen, _) = best.unwrap();
+        cluster.push(chosen);
+        in_cluster.insert(chosen);
+    }
+    Some(cluster)
+}
+
+// Roughly what a cluster is worth with the closeness bonus, ignoring blocking
+fn estimate(packing: &mut Packing, instrument: i64, cluster: &[usize]) -> f64 {
+    let mut total = 0.0;
+    for &i in cluster {
+        let bonus: f64 = cluster
+            .iter()
+            .filter(|&&j| j != i)
+            .map(|&j| 1.0 / distance(&packing.points[i], &packing.points[j]) as f64)
+            .sum();
+        total += packing.value(instrument, i) * (1.0 + bonus);
+    }
+    total
+}
+
+fn out_of_room(problem: &Problem) -> Solution {
+    eprintln!("Clusters ran out of room on the stage, using greedy instead");
+    crate::solver::greedy::solve(problem)
+}
+
+// Play to the v2 closeness bonus: each instrument's musicians are packed
+// together as one cluster, at minimum spacing, placed towards that
+// instrument's fans. The most valuable instruments get first pick of where
+// to go, and each tries a few spots before settling on one
+pub fn solve(problem: &Problem) -> Solution {
+    let mut groups: BTreeMap<i64, Vec<usize>> = BTreeMap::new();
+    for (m, &instrument) in problem.musicians.iter().enumerate() {
+        groups.entry(instrument).or_default().push(m);
+    }
+
+    let mut packing = Packing::new(problem);
+    let mut seeds: Vec<usize> = candidates(problem, MAX_CANDIDATES)
+        .iter()
+        .map(|point| packing.nearest(point))
+        .collect();
+    seeds.sort();
+    seeds.dedup();
+
+    // Most promising instruments first
+    let mut order: Vec<(f64, i64)> = groups
+        .iter()
+        .map(|(&instrument, members)| {
+            let best = seeds
+                .iter()
+                .map(|&i| packing.value(instrument, i))
+                .fold(f64::MIN, f64::max);
+            (best * members.len() as f64, instrument)
+        })
+        .collect();
+    order.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    let mut placements = vec![Position { x: 0.0, y: 0.0 }; problem.musicians.len()];
+    for (_, instrument) in order {
+        let members = &groups[&instrument];
+
+        let free: Vec<usize> = seeds
+            .iter()
+            .copied()
+            .filter(|&i| !packing.taken[i])
+            .collect();
+        let mut starts: Vec<(f64, usize)> = free
+            .into_iter()
+            .map(|i| (packing.value(instrument, i), i))
+            .collect();
+        starts.sort_by(|a, b| b.0.total_cmp(&a.0));
+        starts.truncate(SEEDS);
+        if starts.is_empty() {
+            let center = packing.points[seeds[0]];
+            let Some(start) = packing.free_near(&center, &HashSet::new()) else {
+                return out_of_room(problem);
+            };
+            starts.push((0.0, start));
+        }
+
+        let mut best: Option<(f64, Vec<usize>)> = None;
+        for (_, seed) in starts {
+            let Some(cluster) = grow(&mut packing, instrument, seed, members.len()) else {
+                return out_of_room(problem);
+            };
+            let value = estimate(&mut packing, instrument, &cluster);
+            let better = match &best {
+                Some((best, _)) => value > *best,
+                None => true,
+            };
+            if better {
+                best = Some((value, cluster));
+            }
+        }
+        let (value, cluster) = best.unwrap();
+        eprintln!(
+            "Instrument {}: {} musicians, worth about {}",
+            instrument,
+            members.len(),
+            value
+        );
+
+        for (&m, &i) in members.iter().zip(&cluster) {
+            packing.taken[i] = true;
+            placements[m] = packing.points[i];
+        }
+    }
+
+    let solution = Solution { placements };
+    let delta = DeltaScorer::new(problem, &solution);
+    let factors = closeness(problem, &solution.placements);
+    let together: f64 = (0..problem.musicians.len())
+        .map(|m| factors[m] * delta.contribution(m))
+        .sum();
+    eprintln!(
+        "Expected {} without closeness, about {} with it",
+        delta.score(),
+        together
+    );
+    solution
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repair::violations;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn crowded_stages_stay_legal() {
+        let mut rng = StdRng::seed_from_u64(43);
+        for _ in 0..30 {
+            let musicians = rng.gen_range(10..25);
+            let problem = Problem {
+                room_width: 200.0,
+                room_height: 200.0,
+                stage_width: 60.0,
+                stage_height: 60.0,
+                stage_bottom_left: vec![70.0, 70.0],
+                musicians: (0..musicians).map(|_| rng.gen_range(0..4)).collect(),
+                attendees: (0..10)
+                    .map(|_| Attendee {
+                        x: rng.gen_range(0.0..60.0),
+                        y: rng.gen_range(0.0..200.0),
+                        tastes: (0..4).map(|_| rng.gen_range(-1000.0..1000.0)).collect(),
+                    })
+                    .collect(),
+                pillars: vec![],
+            };
+            let solution = solve(&problem);
+            assert_eq!(violations(&problem, &solution), Vec::<String>::new());
+        }
+    }
+}
@@ -0,0 +1,258 @@
+use crate::candidates::*;
+use crate::deadline;
+use crate::delta::*;
+use crate::icfp::*;
+use crate::repair::*;
+use crate::scorer::*;
+use rand::rngs::ThreadRng;
+use rand::Rng;
+use rapier2d::na::{DMatrix, DVector};
+use std::time::{Duration, Instant};
+
+const TIME_BUDGET: Duration = Duration::from_secs(60);
+// A full covariance matrix over every coordinate gets too slow to update
+// beyond this, so bigger problems go to the greedy solver instead
+const MAX_MUSICIANS: usize = 200;
+// Starting step size, as a fraction of the stage's smaller side
+const INITIAL_SIGMA: f64 = 0.3;
+// Restart once the step size has shrunk this far below where it started...
+const MIN_SIGMA: f64 = 1e-4;
+// ...or the best in each generation hasn't improved for this many generations
+const STAGNATION: usize = 50;
+// Each restart doubles the population (IPOP)
+const POPULATION_GROWTH: usize = 2;
+
+fn evaluate(problem: &Problem, placements: &Placements) -> f64 {
+    let solution = Solution {
+        placements: placements.clone(),
+    };
+    DeltaScorer::new(problem, &solution).score()
+}
+
+fn to_vector(placements: &Placements) -> DVector<f64> {
+    DVector::from_iterator(
+        placements.len() * 2,
+        placements.iter().flat_map(|p| [p.x as f64, p.y as f64]),
+    )
+}
+
+fn to_placements(vector: &DVector<f64>) -> Placements {
+    vector
+        .as_slice()
+        .chunks(2)
+        .map(|xy| Position {
+            x: xy[0] as f32,
+            y: xy[1] as f32,
+        })
+        .collect()
+}
+
+// Everyone somewhere random on the stage, then made legal if there's room.
+// If there isn't, the pipeline's final repair will say so
+fn random_start(problem: &Problem, points: &[Position], rng: &mut ThreadRng) -> Placements {
+    let (x_min, y_min, x_max, y_max) = usable_area(problem);
+    let mut placements: Placements = problem
+        .musicians
+        .iter()
+        .map(|_| Position {
+            x: rng.gen_range(x_min..=x_max),
+            y: rng.gen_range(y_min..=y_max),
+        })
+        .collect();
+    if let Err(error) = repair_placements(problem, &mut placements, points) {
+        eprintln!("{}", error);
+    }
+    placements
+}
+
+// Standard normal sample (Box-Muller)
+fn gaussian(rng: &mut ThreadRng) -> f64 {
+    let u: f64 = 1.0 - rng.gen::<f64>();
+    let v: f64 = rng.gen();
+    (-2.0 * u.ln()).sqrt() * (2.0 * std::f64::consts::PI * v).cos()
+}
+
+// Strategy parameters that only depend on the dimension and population size
+struct Weights {
+    lambda: usize,
+    mu: usize,
+    weights: Vec<f64>,
+    mueff: f64,
+    cc: f64,
+    cs: f64,
+    c1: f64,
+    cmu: f64,
+    damps: f64,
+    chi_n: f64,
+}
+
+impl Weights {
+    fn new(n: usize, lambda: usize) -> Weights {
+        let n_f = n as f64;
+        let mu = lambda / 2;
+        let raw: Vec<f64> = (0..mu)
+            .map(|i| ((lambda as f64 + 1.0) / 2.0).ln() - ((i + 1) as f64).ln())
+            .collect();
+        let total: f64 = raw.iter().sum();
+        let weights: Vec<f64> = raw.iter().map(|w| w / total).collect();
+        let mueff = 1.0 / weights.iter().map(|w| w * w).sum::<f64>();
+
+        let cc = (4.0 + mueff / n_f) / (n_f + 4.0 + 2.0 * mueff / n_f);
+        let cs = (mueff + 2.0) / (n_f + mueff + 5.0);
+        let c1 = 2.0 / ((n_f + 1.3).powi(2) + mueff);
+        let cmu = (1.0 - c1).min(2.0 * (mueff - 2.0 + 1.0 / mueff) / ((n_f + 2.0).powi(2) + mueff));
+        let damps = 1.0 + 2.0 * (((mueff - 1.0) / (n_f + 1.0)).sqrt() - 1.0).max(0.0) + cs;
+        let chi_n = n_f.sqrt() * (1.0 - 1.0 / (4.0 * n_f) + 1.0 / (21.0 * n_f * n_f));
+
+        Weights {
+            lambda,
+            mu,
+            weights,
+            mueff,
+            cc,
+            cs,
+            c1,
+            cmu,
+            damps,
+            chi_n,
+        }
+    }
+}
+
+// One CMA-ES run from a random start, until it converges, stalls or time runs
+// out. Returns the best (repaired) placement it saw
+fn run(
+    problem: &Problem,
+    points: &[Position],
+    lambda: usize,
+    deadline: Instant,
+    rng: &mut ThreadRng,
+) -> (Placements, f64) {
+    let start = random_start(problem, points, rng);
+    let n = start.len() * 2;
+    let w = Weights::new(n, lambda);
+
+    let sigma_0 = INITIAL_SIGMA * problem.stage_width.min(problem.stage_height) as f64;
+    let mut sigma = sigma_0;
+    let mut mean = to_vector(&start);
+    let mut c = DMatrix::<f64>::identity(n, n);
+    let mut b = DMatrix::<f64>::identity(n, n);
+    let mut d = DVector::<f64>::from_element(n, 1.0);
+    let mut pc = DVector::<f64>::zeros(n);
+    let mut ps = DVector::<f64>::zeros(n);
+
+    let mut best_placements = start.clone();
+    let mut best_score = evaluate(problem, &start);
+    let mut last_improvement = 0;
+    let mut generation = 0;
+
+    while Instant::now() < deadline {
+        generation += 1;
+
+        // Sample, repair into something legal, and score. The repaired point
+        // is what goes into the update, so the distribution learns where
+        // legal placements are
+        let mut offspring: Vec<(f64, DVector<f64>)> = (0..w.lambda)
+            .map(|_| {
+                let z = DVector::<f64>::from_fn(n, |_, _| gaussian(rng));
+                let x = &mean + sigma * (&b * d.component_mul(&z));
+                let mut placements = to_placements(&x);
+                let score = match repair_placements(problem, &mut placements, points) {
+                    Ok(()) => evaluate(problem, &placements),
+                    Err(_) => f64::NEG_INFINITY,
+                };
+                (score, to_vector(&placements))
+            })
+            .collect();
+        offspring.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+        if offspring[0].0 > best_score {
+            best_score = offspring[0].0;
+            best_placements = to_placements(&offspring[0].1);
+            last_improvement = generation;
+        }
+
+        let old_mean = mean.clone();
+        mean = DVector::<f64>::zeros(n);
+        for (weight, (_, x)) in w.weights.iter().zip(&offspring) {
+            mean += *weight * x;
+        }
+        let step = (&mean - &old_mean) / sigma;
+
+        // C^-1/2 from the eigendecomposition
+        let inv_sqrt_c = &b * DMatrix::from_diagonal(&d.map(|d| 1.0 / d)) * b.transpose();
+        ps = (1.0 - w.cs) * &ps + (w.cs * (2.0 - w.cs) * w.mueff).sqrt() * (&inv_sqrt_c * &step);
+        let ps_norm = ps.norm();
+        let hsig = ps_norm / (1.0 - (1.0 - w.cs).powi(2 * generation as i32)).sqrt() / w.chi_n
+            < 1.4 + 2.0 / (n as f64 + 1.0);
+        let hsig = if hsig { 1.0 } else { 0.0 };
+        pc = (1.0 - w.cc) * &pc + hsig * (w.cc * (2.0 - w.cc) * w.mueff).sqrt() * &step;
+
+        let mut rank_mu = DMatrix::<f64>::zeros(n, n);
+        for (weight, (_, x)) in w.weights.iter().zip(offspring.iter().take(w.mu)) {
+            let y = (x - &old_mean) / sigma;
+            rank_mu += *weight * &y * y.transpose();
+        }
+        c = (1.0 - w.c1 - w.cmu) * &c
+            + w.c1 * (&pc * pc.transpose() + (1.0 - hsig) * w.cc * (2.0 - w.cc) * &c)
+            + w.cmu * rank_mu;
+        sigma *= ((w.cs / w.damps) * (ps_norm / w.chi_n - 1.0)).exp();
+
+        // Keep C symmetric against rounding, then re-decompose it
+        c = (&c + c.transpose()) / 2.0;
+        let eigen = c.clone().symmetric_eigen();
+        b = eigen.eigenvectors;
+        d = eigen.eigenvalues.map(|e| e.max(1e-20).sqrt());
+
+        if sigma < MIN_SIGMA * sigma_0 || generation - last_improvement > STAGNATION {
+            break;
+        }
+    }
+    eprintln!(
+        "CMA-ES run with population {} stopped after {} generations at {}",
+        lambda, generation, best_score
+    );
+
+    (best_placements, best_score)
+}
+
+// CMA-ES over the 2N coordinate vector, restarting with a doubled population
+// every time a run converges or stalls (IPOP-CMA-ES)
+pub fn solve(problem: &Problem) -> Solution {
+    let musician_count = problem.musicians.len();
+    if musician_count > MAX_MUSICIANS {
+        eprintln!(
+            "{} musicians is too many for CMA-ES, using greedy instead",
+            musician_count
+        );
+        return crate::solver::greedy::solve(problem);
+    }
+    if musician_count == 0 {
+        return Solution { placements: vec![] };
+    }
+
+    let mut rng = rand::thread_rng();
+    let points = candidates(problem, MAX_CANDIDATES);
+    let n = musician_count * 2;
+    let mut lambda = 4 + (3.0 * (n as f64).ln()).floor() as usize;
+
+    let deadline = Instant::now() + deadline::budget(TIME_BUDGET);
+    let mut best_placements: Placements = Vec::new();
+    let mut best_score = f64::NEG_INFINITY;
+    while Instant::now() < deadline {
+        let (placements, score) = run(problem, &points, lambda, deadline, &mut rng);
+        if score > best_score {
+            best_score = score;
+            best_placements = placements;
+            eprintln!("New best {}", best_score);
+            report(best_score);
+        }
+        lambda *= POPULATION_GROWTH;
+    }
+
+    let solution = Solution {
+        placements: best_placements,
+    };
+    eprintln!("CMA-ES scored {}", scorer(problem, &solution));
+    solution
+}
@@ -0,0 +1,243 @@
+use crate::candidates::*;
+use crate::deadline;
+use crate::geometry::*;
+use crate::icfp::*;
+use crate::scorer::*;
+use crate::shadow::*;
+use crate::symmetry::*;
+use std::time::{Duration, Instant};
+
+// The search is exponential in the number of musicians, so only a coarse set
+// of points is tried
+const COARSE_CANDIDATES: usize = 200;
+// Past this, give up on proving anything and go with the best found so far
+const TIME_BUDGET: Duration = Duration::from_secs(300);
+const SCORER_TOLERANCE: f64 = 0.0001;
+
+struct Search<'a> {
+    problem: &'a Problem,
+    points: Vec<Position>,
+    attendees: Vec<Position>,
+    // Which attendees can see each point past the pillars
+    visible: Vec<Vec<bool>>,
+    // Musicians in the order they're placed, same-instrument ones together
+    order: Vec<usize>,
+    // Points to try for each musician in `order`, most promising first
+    choices: Vec<Vec<usize>>,
+    // The most any musician of each one in `order` could ever add: the
+    // fake-max score, where nobody gets in anyone's way
+    ceiling: Vec<f64>,
+    // Point index for each musician in `order` placed so far
+    placed: Vec<usize>,
+    // The score to beat, starting from the greedy solution's, and the best
+    // placement found that beats it
+    floor: f64,
+    best: Option<Vec<usize>>,
+    nodes: u64,
+    started: Instant,
+    time_budget: Duration,
+    out_of_time: bool,
+}
+
+impl<'a> Search<'a> {
+    // What the musicians placed so far get, counting lines of sight they
+    // block for each other. With `optimistic` set it's an upper bound on
+    // what they'll end up with: later musicians can only block more, so
+    // dislikes are left out and likes counted as if they'll stay clear
+    fn placed_value(&self, optimistic: bool) -> f64 {
+        let mut total = 0.0;
+        for (k, &p) in self.placed.iter().enumerate() {
+            let instrument = self.problem.musicians[self.order[k]];
+            let at = &self.points[p];
+            for (a, attendee) in self.problem.attendees.iter().enumerate() {
+                if !self.visible[p][a] {
+                    continue;
+                }
+                let blocked = self.placed.iter().enumerate().any(|(j, &q)| {
+                    j != k && is_blocked(at, &self.attendees[a], &self.points[q], MUSICIAN_RADIUS)
+                });
+                if blocked {
+                    continue;
+                }
+                let value = impact(attendee, instrument, at) as f64;
+                total += if optimistic { value.max(0.0) } else { value };
+            }
+        }
+        total
+    }
+
+    fn fits(&self, point: usize) -> bool {
+        self.placed
+            .iter()
+            .all(|&q| distance(&self.points[q], &self.points[point]) >= MUSICIAN_SPACING)
+    }
+
+    fn search(&mut self) {
+        self.nodes += 1;
+        if self.out_of_time || self.started.elapsed() > self.time_budget {
+            self.out_of_time = true;
+            return;
+        }
+
+        let depth = self.placed.len();
+        if depth == self.order.len() {
+            let value = self.placed_value(false);
+            if value > self.floor {
+                eprintln!("New best {}", value);
+                report(value);
+                self.floor = value;
+                self.best = Some(self.placed.clone());
+            }
+            return;
+        }
+
+        let bound = self.placed_value(true) + self.ceiling[depth..].iter().sum::<f64>();
+        if bound <= self.floor {
+            return;
+        }
+
+        // Musicians playing the same instrument are interchangeable, so each
+        // only takes points after the one before it
+        let same_as_previous = depth > 0
+            && self.problem.musicians[self.order[depth]]
+                == self.problem.musicians[self.order[depth - 1]];
+        let start = if same_as_previous {
+            let previous = self.placed[depth - 1];
+            self.choices[depth]
+                .iter()
+                .position(|&p| p == previous)
+                .unwrap()
+                + 1
+        } else {
+            0
+        };
+
+        for i in start..self.choices[depth].len() {
+            let point = self.choices[depth][i];
+            if !self.fits(point) {
+                continue;
+            }
+            self.placed.push(point);
+            self.search();
+            self.placed.pop();
+        }
+    }
+}
+
+// Branch and bound over a set of candidate points: every way of putting the
+// musicians on them is considered, except those that provably can't beat the
+// best found so far. The greedy solution is the one to beat from the start,
+// which prunes from the first node and leaves something to return if time
+// runs out before the search gets anywhere. Unless it runs out of time, the
+// answer is the best possible on those points, which makes it a handy check
+// on what the heuristic solvers get for tiny problems
+pub fn solve(problem: &Problem) -> Solution {
+    let incumbent = crate::solver::greedy::solve(problem);
+    let incumbent_score = scorer(problem, &incumbent) as f64;
+
+    let points = candidates(problem, COARSE_CANDIDATES);
+    let attendees: Vec<Position> = problem
+        .attendees
+        .iter()
+        .map(|a| Position { x: a.x, y: a.y })
+        .collect();
+    let shadows = ShadowMap::new(problem);
+    let visible: Vec<Vec<bool>> = points
+        .iter()
+        .map(|point| {
+            (0..attendees.len())
+                .map(|a| shadows.is_visible(a, point))
+                .collect()
+        })
+        .collect();
+
+    let order: Vec<usize> = groups(problem).into_iter().flatten().collect();
+
+    // The most each point could be worth to each musician, with nobody else
+    // in the way and only the attendees that like it counted
+    let mut choices = Vec::new();
+    let mut ceiling = Vec::new();
+    for &m in &order {
+        let instrument = problem.musicians[m];
+        let mut values: Vec<(f64, usize)> = points
+            .iter()
+            .enumerate()
+            .map(|(p, point)| {
+                let value: f64 = problem
+                    .attendees
+                    .iter()
+                    .enumerate()
+                    .filter(|(a, _)| visible[p][*a])
+                    .map(|(_, attendee)| (impact(attendee, instrument, point) as f64).max(0.0))
+                    .sum();
+                (value, p)
+            })
+            .collect();
+        values.sort_by(|a, b| b.0.total_cmp(&a.0));
+        ceiling.push(values.first().map_or(0.0, |v| v.0));
+        choices.push(values.into_iter().map(|(_, p)| p).collect());
+    }
+
+    eprintln!(
+        "Searching {} points for {} musicians",
+        points.len(),
+        order.len()
+    );
+    let mut search = Search {
+        problem,
+        points,
+        attendees,
+        visible,
+        order,
+        choices,
+        ceiling,
+        placed: Vec::new(),
+        floor: incumbent_score,
+        best: None,
+        nodes: 0,
+        started: Instant::now(),
+        time_budget: deadline::budget(TIME_BUDGET),
+        out_of_time: false,
+    };
+    search.search();
+
+    let value = search.floor;
+    let Some(placed) = search.best else {
+        if search.out_of_time {
+            eprintln!(
+                "Ran out of time after {} nodes without beating greedy's {}",
+                search.nodes, value
+            );
+        } else {
+            eprintln!(
+                "Nothing on these points beats greedy's {} ({} nodes)",
+                value, search.nodes
+            );
+        }
+        return incumbent;
+    };
+    let mut placements = vec![Position { x: 0.0, y: 0.0 }; problem.musicians.len()];
+    for (&m, &p) in search.order.iter().zip(&placed) {
+        placements[m] = search.points[p];
+    }
+    let solution = Solution { placements };
+
+    if search.out_of_time {
+        eprintln!(
+            "Ran out of time after {} nodes, best found {}",
+            search.nodes, value
+        );
+    } else {
+        eprintln!(
+            "Proven best on these points after {} nodes: {}",
+            search.nodes, value
+        );
+    }
+    // The scorer adds up in f32, so only a bigger difference than rounding
+    // would explain means one of us is wrong
+    let scored = scorer(problem, &solution);
+    if (scored as f64 - value).abs() > value.abs() * SCORER_TOLERANCE {
+        eprintln!("Scorer disagrees and says {}", scored);
+    }
+    solution
+}
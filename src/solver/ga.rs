@@ -1,16 +1,30 @@
 use std::collections::HashSet;
 use rand::Rng;
 use crate::icfp::*;
+use crate::params::*;
 use crate::scorer::*;
+use crate::symmetry::*;
+
+pub const PARAMS: &[Param] = &[
+    Param { name: "population", min: 10.0, max: 500.0, default: 100.0, integer: true },
+    // How much of the population makes it into the next generation, best
+    // first. A share so there can't be more survivors than population
+    Param { name: "survivors", min: 0.02, max: 0.5, default: 0.2, integer: false },
+    Param { name: "generations", min: 10.0, max: 1000.0, default: 100.0, integer: true },
+];
+
+// Tiny problems might not have enough different solutions to fill a
+// population, so after this many copies just let them in
+const MAX_DUPLICATES: usize = 1000;
 
 // // , Serialize, Deserialize)]
 #[derive(Debug, Clone)]
-struct Individual {
-    solution: Solution,
-    score: f32
+pub(crate) struct Individual {
+    pub(crate) solution: Solution,
+    pub(crate) score: f32
 }
 
-fn generate_random_individual(problem: &Problem) -> Individual {
+pub(crate) fn generate_random_individual(problem: &Problem) -> Individual {
     let mut rng = rand::thread_rng();
     let mut players: Vec<Position> = Vec::new();
 
@@ -39,9 +53,9 @@ fn generate_random_individual(problem: &Problem) -> Individual {
         }
     }
 
-    let solution = Solution {
+    let solution = canonical(&groups(problem), &Solution {
         placements: players,
-    };
+    });
 
     let score = scorer(&problem, &solution);
 
@@ -51,12 +65,27 @@ fn generate_random_individual(problem: &Problem) -> Individual {
     }
 }
 
-fn mutate_swap_placements(placements: Placements) -> Placements {
-    let mut placements = placements.clone();
+// Pick two placements to trade musicians (they might be the same one)
+pub(crate) fn pick_swap(placements: &Placements) -> (usize, usize) {
     let mut rng = rand::thread_rng();
     let placement_1_n = rng.gen_range(0..placements.len());
-    let placement_1_v = placements[placement_1_n].clone();
     let placement_2_n = rng.gen_range(0..placements.len());
+    (placement_1_n, placement_2_n)
+}
+
+// Swapping two musicians who play the same instrument changes nothing, so
+// look for two who don't (giving up if everyone plays the same thing)
+fn mutate_swap_placements(problem: &Problem, placements: Placements) -> Placements {
+    let mut placements = placements.clone();
+    let mut swap = pick_swap(&placements);
+    for _ in 0..100 {
+        if problem.musicians[swap.0] != problem.musicians[swap.1] {
+            break;
+        }
+        swap = pick_swap(&placements);
+    }
+    let (placement_1_n, placement_2_n) = swap;
+    let placement_1_v = placements[placement_1_n].clone();
     let placement_2_v = placements[placement_2_n].clone();
     placements[placement_1_n] = placement_2_v;
     placements[placement_2_n] = placement_1_v;
@@ -70,8 +99,8 @@ fn generate_offspring(problem: &Problem, individual_a: &Individual, individual_b
     // Swap two placements
     if rng.gen_ratio(10, 100) {
         let placements = individual_a.solution.placements.clone();
-        let placements = mutate_swap_placements(placements.clone());
-        let solution = Solution { placements: placements };
+        let placements = mutate_swap_placements(problem, placements.clone());
+        let solution = canonical(&groups(problem), &Solution { placements: placements });
         let score = scorer(&problem, &solution);
         return Individual { solution: solution, score: score };
     }
@@ -95,12 +124,48 @@ fn generate_offspring(problem: &Problem, individual_a: &Individual, individual_b
     random_individual
 }
 
-pub fn solve(problem: &Problem) -> Solution {
+// Keep the best of a sorted population and breed the rest back from them,
+// leaving it sorted again. Individuals are kept in canonical form, so copies
+// of the same solution (even with same-instrument musicians swapped around)
+// are spotted and thrown out, unless that's all we can come up with
+pub(crate) fn next_generation(problem: &Problem, population: &mut Vec<Individual>, survivors: usize, size: usize) {
     let mut rng = rand::thread_rng();
+    let groups = groups(problem);
+
+    // Select top X
+    population.truncate(survivors.max(1));
+    let mut seen: HashSet<Key> = population.iter().map(|i| key(&groups, &i.solution)).collect();
+
+    // Fill up new population
+    let mut duplicates = 0;
+    while population.len() < size {
+        let individual_a = &population[rng.gen_range(0..population.len())];
+        let individual_b = &population[rng.gen_range(0..population.len())];
+        let new_individual = generate_offspring(problem, individual_a, individual_b);
+        if !seen.insert(key(&groups, &new_individual.solution)) && duplicates < MAX_DUPLICATES {
+            duplicates += 1;
+            continue;
+        }
+        population.push(new_individual);
+    }
+
+    // Sort
+    population.sort_by(|b, a| a.score.partial_cmp(&b.score).unwrap());
+}
+
+// At least one, and never the whole population
+pub(crate) fn survivor_count(params: &Params) -> usize {
+    let size = params.count("population");
+    ((size as f64 * params.get("survivors")).round() as usize).clamp(1, size.max(1))
+}
+
+pub fn solve_with_params(problem: &Problem, params: &Params) -> Solution {
+    let size = params.count("population");
+    let survivors = survivor_count(params);
     let mut population: Vec<Individual> = Vec::new();
 
     // Set up the initial population
-    for _ in 0..100 {
+    for _ in 0..size {
         let random_individual = generate_random_individual(problem);
         population.push(random_individual);
     }
@@ -108,21 +173,13 @@ pub fn solve(problem: &Problem) -> Solution {
     // Start with a sorted population
     population.sort_by(|b, a| a.score.partial_cmp(&b.score).unwrap());
 
-    for generation in 0..100 {
-        // Select top X
-        population.truncate(20);
-
-        // Fill up new population
-        while population.len() < 100 {
-            let individual_a = &population[rng.gen_range(0..population.len())];
-            let individual_b = &population[rng.gen_range(0..population.len())];
-            let new_individual = generate_offspring(problem, individual_a, individual_b);
-            population.push(new_individual);
+    for generation in 0..params.count("generations") {
+        let best = population[0].score;
+        next_generation(problem, &mut population, survivors, size);
+        if population[0].score > best {
+            report(population[0].score as f64);
         }
 
-        // Sort
-        population.sort_by(|b, a| a.score.partial_cmp(&b.score).unwrap());
-
         // Stats!
         let sum: f32 = population.iter().map(|i| i.score).sum();
         let avg = sum / population.len() as f32;
@@ -134,3 +191,7 @@ pub fn solve(problem: &Problem) -> Solution {
     solution
 }
 
+pub fn solve(problem: &Problem) -> Solution {
+    solve_with_params(problem, &Params::defaults(PARAMS))
+}
+
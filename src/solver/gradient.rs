@@ -0,0 +1,143 @@
+use crate::candidates::*;
+use crate::deadline;
+use crate::delta::*;
+use crate::geometry::*;
+use crate::icfp::*;
+use crate::repair::*;
+use crate::scorer::*;
+use crate::surrogate::*;
+use std::time::{Duration, Instant};
+
+const TIME_BUDGET: Duration = Duration::from_secs(60);
+// How far musicians move each step, shrinking from the first to the second
+// over the run
+const STEP_START: f32 = 5.0;
+const STEP_END: f32 = 0.1;
+const MOMENTUM: f32 = 0.8;
+// How hard musicians get pushed back per unit they stray past the stage
+// margin or into each other, against the score's pull of 1
+const BARRIER: f32 = 10.0;
+// Repair and properly score the current placement every this many steps
+const CHECK_EVERY: usize = 10;
+
+fn evaluate(problem: &Problem, placements: &Placements) -> f64 {
+    let solution = Solution {
+        placements: placements.clone(),
+    };
+    DeltaScorer::new(problem, &solution).score()
+}
+
+// Which way each musician should go: uphill on the smoothed score, plus the
+// barriers keeping them on the stage and out of each other's way. Capped to a
+// length of 1
+fn directions(problem: &Problem, placements: &Placements) -> Vec<(f32, f32)> {
+    let smooth = smooth_score(problem, placements);
+    let (x_min, y_min, x_max, y_max) = usable_area(problem);
+
+    let mut directions: Vec<(f32, f32)> = smooth
+        .gradient
+        .iter()
+        .zip(placements)
+        .map(|(&(gx, gy), p)| {
+            let norm = (gx * gx + gy * gy).sqrt();
+            let (mut dx, mut dy) = if norm > 0.0 {
+                ((gx / norm) as f32, (gy / norm) as f32)
+            } else {
+                (0.0, 0.0)
+            };
+            dx += BARRIER * ((x_min - p.x).max(0.0) - (p.x - x_max).max(0.0));
+            dy += BARRIER * ((y_min - p.y).max(0.0) - (p.y - y_max).max(0.0));
+            (dx, dy)
+        })
+        .collect();
+
+    for a in 0..placements.len() {
+        for b in a + 1..placements.len() {
+            let gap = distance(&placements[a], &placements[b]);
+            if gap >= MUSICIAN_SPACING || gap == 0.0 {
+                continue;
+            }
+            let push = BARRIER * (MUSICIAN_SPACING - gap) / gap;
+            let (dx, dy) = (
+                placements[b].x - placements[a].x,
+                placements[b].y - placements[a].y,
+            );
+            directions[a].0 -= push * dx;
+            directions[a].1 -= push * dy;
+            directions[b].0 += push * dx;
+            directions[b].1 += push * dy;
+        }
+    }
+
+    for (dx, dy) in directions.iter_mut() {
+        let norm = (*dx * *dx + *dy * *dy).sqrt();
+        if norm > 1.0 {
+            *dx /= norm;
+            *dy /= norm;
+        }
+    }
+    directions
+}
+
+// Gradient ascent with momentum on the smoothed score. The smoothed score
+// can see a blocked musician is nearly clear, or an open line is about to be
+// blocked, where the real score is flat until it jumps
+pub fn improve(problem: &Problem, solution: &Solution) -> Solution {
+    if problem.musicians.is_empty() {
+        return solution.clone();
+    }
+
+    let points = candidates(problem, MAX_CANDIDATES);
+    let mut placements = solution.placements.clone();
+    let mut velocity = vec![(0.0, 0.0); placements.len()];
+    let mut best_placements = placements.clone();
+    let mut best_score = evaluate(problem, &placements);
+    eprintln!("Gradient ascent starting from {}", best_score);
+
+    let time_budget = deadline::budget(TIME_BUDGET);
+    let start = Instant::now();
+    let mut step = 0;
+    while start.elapsed() < time_budget {
+        step += 1;
+        let progress = start.elapsed().as_secs_f32() / time_budget.as_secs_f32();
+        let step_size = STEP_START * (STEP_END / STEP_START).powf(progress);
+
+        let directions = directions(problem, &placements);
+        for ((position, velocity), (dx, dy)) in placements
+            .iter_mut()
+            .zip(velocity.iter_mut())
+            .zip(directions)
+        {
+            velocity.0 = MOMENTUM * velocity.0 + (1.0 - MOMENTUM) * dx;
+            velocity.1 = MOMENTUM * velocity.1 + (1.0 - MOMENTUM) * dy;
+            position.x += step_size * velocity.0;
+            position.y += step_size * velocity.1;
+        }
+
+        if step % CHECK_EVERY == 0 {
+            let mut legal = placements.clone();
+            if repair_placements(problem, &mut legal, &points).is_err() {
+                continue;
+            }
+            let score = evaluate(problem, &legal);
+            if score > best_score {
+                best_score = score;
+                best_placements = legal;
+                eprintln!("Step {}: new best {}", step, best_score);
+                report(best_score);
+            }
+        }
+    }
+    eprintln!("Gradient ascent ran {} steps", step);
+
+    let solution = Solution {
+        placements: best_placements,
+    };
+    eprintln!("Gradient ascent scored {}", scorer(problem, &solution));
+    solution
+}
+
+pub fn solve(problem: &Problem) -> Solution {
+    let start = crate::solver::greedy::solve(problem);
+    improve(problem, &start)
+}
@@ -1,8 +1,77 @@
+use crate::deadline;
 use crate::icfp::*;
+use crate::params::*;
 use crate::scorer::*;
 use rapier2d::prelude::*;
+use std::time::{Duration, Instant};
 
-fn solve_once(problem: &Problem) -> Solution {
+pub const PARAMS: &[Param] = &[
+    // Separate simulations from random starts
+    Param {
+        name: "attempts",
+        min: 1.0,
+        max: 200.0,
+        default: 100.0,
+        integer: true,
+    },
+    Param {
+        name: "steps",
+        min: 10.0,
+        max: 1000.0,
+        default: 200.0,
+        integer: true,
+    },
+    // How hard musicians get pushed towards where their audience is, as an
+    // acceleration, easing off from the start to the end of the simulation so
+    // they can settle
+    Param {
+        name: "force_start",
+        min: 1.0,
+        max: 200.0,
+        default: 50.0,
+        integer: false,
+    },
+    Param {
+        name: "force_end",
+        min: 0.1,
+        max: 10.0,
+        default: 1.0,
+        integer: false,
+    },
+    Param {
+        name: "damping",
+        min: 0.0,
+        max: 10.0,
+        default: 1.0,
+        integer: false,
+    },
+];
+// Stage walls sit this far in from the edge of the stage, so a musician
+// (radius 5) leaning on one is still 10 from the edge
+const WALL_INSET: f32 = 5.0;
+// Every step of every attempt scores the whole stage, so big problems would
+// take all day. Past this, go with the best found so far
+const TIME_BUDGET: Duration = Duration::from_secs(120);
+
+// Which way musician `m` at `at` would most like to move: the direction that
+// raises their impact fastest, summed over the whole audience
+fn preference(problem: &Problem, m: usize, at: &Vector<Real>) -> Vector<Real> {
+    let instrument = problem.musicians[m] as usize;
+    let mut direction = vector![0.0, 0.0];
+    for attendee in &problem.attendees {
+        let offset = vector![attendee.x - at.x, attendee.y - at.y];
+        let distance_squared = offset.norm_squared();
+        direction += offset * (attendee.tastes[instrument] / (distance_squared * distance_squared));
+    }
+    let norm = direction.norm();
+    if norm > 0.0 {
+        direction / norm
+    } else {
+        direction
+    }
+}
+
+fn solve_once(problem: &Problem, params: &Params, deadline: Instant) -> Solution {
     let random_start = crate::solver::random::solve(&problem);
 
     let (mut rigid_body_set, mut collider_set, players) = setup_bodies(&random_start, &problem);
@@ -11,7 +80,7 @@ fn solve_once(problem: &Problem) -> Solution {
     // let mut qp = QueryPipeline::new();
 
     /* Create other structures necessary for the simulation. */
-    let gravity = vector![10.0, 10.0];
+    let gravity = vector![0.0, 0.0];
     let integration_parameters = IntegrationParameters::default();
     let mut physics_pipeline = PhysicsPipeline::new();
     let mut island_manager = IslandManager::new();
@@ -26,8 +95,34 @@ fn solve_once(problem: &Problem) -> Solution {
     let mut best_solution = random_start;
     let mut best_score = scorer(&problem, &best_solution);
 
+    for player in &players {
+        rigid_body_set
+            .get_mut(*player)
+            .unwrap()
+            .set_linear_damping(params.get("damping") as f32);
+    }
+
     /* Run the game loop, stepping the simulation once per frame. */
-    for _ in 0..200 {
+    let steps = params.count("steps");
+    let force_start = params.get("force_start") as f32;
+    let force_end = params.get("force_end") as f32;
+    for step in 0..steps {
+        if Instant::now() >= deadline {
+            break;
+        }
+
+        // Push everyone towards their own audience, more gently as we go
+        let progress = step as f32 / steps as f32;
+        let strength = force_start * (force_end / force_start).powf(progress);
+        for (m, player) in players.iter().enumerate() {
+            let body = rigid_body_set.get_mut(*player).unwrap();
+            let force = preference(problem, m, &body.position().translation.vector)
+                * strength
+                * body.mass();
+            body.reset_forces(true);
+            body.add_force(force, true);
+        }
+
         physics_pipeline.step(
             &gravity,
             &integration_parameters,
@@ -73,51 +168,34 @@ fn solve_once(problem: &Problem) -> Solution {
     best_solution
 }
 
-fn setup_bodies(
+pub(crate) fn setup_bodies(
     solution: &Solution,
     problem: &Problem,
 ) -> (RigidBodySet, ColliderSet, Vec<RigidBodyHandle>) {
     let mut rigid_body_set = RigidBodySet::new();
     let mut collider_set = ColliderSet::new();
 
-    // build the walls of the stage
-    let bottom = ColliderBuilder::cuboid(0.1, problem.stage_width).build();
-    let top = ColliderBuilder::cuboid(0.1, problem.stage_height).build();
-    let left = ColliderBuilder::cuboid(problem.stage_height, 0.1).build();
-    let right = ColliderBuilder::cuboid(problem.stage_height, 0.1).build();
-
-    let bottom_left = RigidBodyBuilder::fixed()
-        .translation(vector![
-            problem.stage_bottom_left[0],
-            problem.stage_bottom_left[1]
-        ])
-        .build();
-
-    let bottom_left_handle = rigid_body_set.insert(bottom_left);
-
-    let top_left = RigidBodyBuilder::fixed()
-        .translation(vector![
-            problem.stage_bottom_left[0],
-            problem.stage_bottom_left[1] + problem.stage_height
-        ])
-        .build();
-
-    let top_left_handle = rigid_body_set.insert(top_left);
-
-    let bottom_right = RigidBodyBuilder::fixed()
-        .translation(vector![
-            problem.stage_bottom_left[0] + problem.stage_width,
-            problem.stage_bottom_left[1]
-        ])
-        .build();
-
-    let bottom_right_handle = rigid_body_set.insert(bottom_right);
-
-    // Add the walls to the world
-    collider_set.insert_with_parent(top, top_left_handle, &mut rigid_body_set);
-    collider_set.insert_with_parent(bottom, bottom_left_handle, &mut rigid_body_set);
-    collider_set.insert_with_parent(left, bottom_right_handle, &mut rigid_body_set);
-    collider_set.insert_with_parent(right, bottom_left_handle, &mut rigid_body_set);
+    // build the walls of the stage, just far enough in that musicians
+    // leaning on them are still a legal distance from the edge
+    let x_min = problem.stage_bottom_left[0] + WALL_INSET;
+    let y_min = problem.stage_bottom_left[1] + WALL_INSET;
+    let x_max = problem.stage_bottom_left[0] + problem.stage_width - WALL_INSET;
+    let y_max = problem.stage_bottom_left[1] + problem.stage_height - WALL_INSET;
+    let (x_mid, y_mid) = ((x_min + x_max) / 2.0, (y_min + y_max) / 2.0);
+    let (half_width, half_height) = (problem.stage_width / 2.0, problem.stage_height / 2.0);
+
+    let walls = [
+        (half_width, 0.1, x_mid, y_min - 0.1),
+        (half_width, 0.1, x_mid, y_max + 0.1),
+        (0.1, half_height, x_min - 0.1, y_mid),
+        (0.1, half_height, x_max + 0.1, y_mid),
+    ];
+    for (half_x, half_y, x, y) in walls {
+        let wall = ColliderBuilder::cuboid(half_x, half_y).build();
+        let body =
+            rigid_body_set.insert(RigidBodyBuilder::fixed().translation(vector![x, y]).build());
+        collider_set.insert_with_parent(wall, body, &mut rigid_body_set);
+    }
 
     let mut musician_handles: Vec<RigidBodyHandle> = Vec::new();
     // Loop over musicans and add them to the world
@@ -148,17 +226,23 @@ fn setup_bodies(
     (rigid_body_set, collider_set, musician_handles)
 }
 
-pub fn solve(problem: &Problem) -> Solution {
-    let n = 100;
-    let mut best_score = 0.0;
+pub fn solve_with_params(problem: &Problem, params: &Params) -> Solution {
+    let n = params.count("attempts");
+    let deadline = Instant::now() + deadline::budget(TIME_BUDGET);
+    let mut best_score = f32::NEG_INFINITY;
     let mut best_solution = Solution { placements: vec![] };
     for attempt in 0..n {
+        if attempt > 0 && Instant::now() >= deadline {
+            eprintln!("Out of time after {} attempts", attempt);
+            break;
+        }
         eprint!("Attempt {}... ", attempt);
-        let solution = solve_once(problem);
+        let solution = solve_once(problem, params, deadline);
         let score = scorer(&problem, &solution);
-        eprintln!("score {}", score);
+        eprintln!("scored {}", score);
         if score > best_score {
             eprintln!("  NEW WINNER");
+            report(score as f64);
             best_score = score;
             best_solution = solution;
         }
@@ -166,3 +250,7 @@ pub fn solve(problem: &Problem) -> Solution {
     eprintln!("best score {}", best_score);
     best_solution
 }
+
+pub fn solve(problem: &Problem) -> Solution {
+    solve_with_params(problem, &Params::defaults(PARAMS))
+}
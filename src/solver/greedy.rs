@@ -0,0 +1,323 @@
+use crate::candidates::*;
+use crate::geometry::*;
+use crate::icfp::*;
+use crate::scorer::*;
+use crate::shadow::*;
+use std::collections::HashSet;
+
+// Every candidate point keeps a row per attendee and a row per instrument, so
+// cap how many there are to keep memory and time reasonable
+const MAX_POINTS: usize = 8000;
+const MAX_POINT_ATTENDEES: usize = 30_000_000;
+const MAX_POINT_INSTRUMENTS: usize = 20_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Spot {
+    Free,
+    // Too close to a musician to be used any more
+    Taken,
+    // Holds a musician playing this instrument
+    Placed(i64),
+}
+
+// How many candidate points we can afford to track for this problem
+fn point_limit(problem: &Problem) -> usize {
+    let instruments: HashSet<i64> = problem.musicians.iter().cloned().collect();
+    MAX_POINTS
+        .min(MAX_POINT_ATTENDEES / problem.attendees.len().max(1))
+        .min(MAX_POINT_INSTRUMENTS / instruments.len().max(1))
+        .max(2 * problem.musicians.len())
+}
+
+struct Greedy<'a> {
+    problem: &'a Problem,
+    attendees: Vec<Position>,
+    instruments: Vec<i64>,
+    points: Vec<Position>,
+    spots: Vec<Spot>,
+    // Attendees sorted by angle as seen from each point
+    around: Vec<Vec<(f32, u32)>>,
+    // How many musicians and pillars are in the way from each point to each
+    // attendee
+    blocked: Vec<Vec<u16>>,
+    // What each instrument would add from each point, ignoring blocking it
+    // would cause
+    gain: Vec<Vec<f32>>,
+    // What the musicians already placed would lose to a musician at each point
+    loss: Vec<f32>,
+    // Best instrument still waiting to be placed at each point
+    best: Vec<Option<(usize, f32)>>,
+    stale: Vec<bool>,
+}
+
+impl<'a> Greedy<'a> {
+    fn new(problem: &'a Problem) -> Greedy<'a> {
+        let attendees: Vec<Position> = problem
+            .attendees
+            .iter()
+            .map(|a| Position { x: a.x, y: a.y })
+            .collect();
+        let mut instruments = problem.musicians.clone();
+        instruments.sort();
+        instruments.dedup();
+        let points = candidates(problem, point_limit(problem));
+        eprintln!(
+            "{} candidate points for {} musicians",
+            points.len(),
+            problem.musicians.len()
+        );
+
+        let around = points
+            .iter()
+            .map(|point| sorted_by_angle(point, &attendees))
+            .collect();
+
+        let mut greedy = Greedy {
+            problem,
+            blocked: vec![vec![0; attendees.len()]; points.len()],
+            gain: vec![vec![0.0; instruments.len()]; points.len()],
+            loss: vec![0.0; points.len()],
+            best: vec![None; points.len()],
+            stale: vec![true; points.len()],
+            spots: vec![Spot::Free; points.len()],
+            attendees,
+            instruments,
+            points,
+            around,
+        };
+
+        let shadows = ShadowMap::new(problem);
+        for p in 0..greedy.points.len() {
+            for a in 0..greedy.attendees.len() {
+                if !shadows.is_visible(a, &greedy.points[p]) {
+                    greedy.blocked[p][a] += 1;
+                }
+            }
+        }
+
+        for p in 0..greedy.points.len() {
+            for (a, attendee) in problem.attendees.iter().enumerate() {
+                if greedy.blocked[p][a] == 0 {
+                    for (i, &instrument) in greedy.instruments.iter().enumerate() {
+                        greedy.gain[p][i] += impact(attendee, instrument, &greedy.points[p]);
+                    }
+                }
+            }
+        }
+
+        greedy
+    }
+
+    // Count a circle against every line of sight from point `p` it cuts, and
+    // report the ones that it newly blocks
+    fn block(&mut self, p: usize, center: &Position, radius: f32, mut newly: impl FnMut(usize)) {
+        let point = self.points[p];
+        let attendees = &self.attendees;
+        let blocked = &mut self.blocked[p];
+        in_window(
+            &self.around[p],
+            angle(&point, center),
+            window_width(radius, distance(&point, center)),
+            |a| {
+                if is_blocked(&point, &attendees[a], center, radius) {
+                    blocked[a] += 1;
+                    if blocked[a] == 1 {
+                        newly(a);
+                    }
+                }
+            },
+        );
+    }
+
+    fn best_at(&mut self, p: usize, waiting: &[Vec<usize>]) -> Option<(usize, f32)> {
+        if self.stale[p] {
+            self.best[p] = self.gain[p]
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| !waiting[*i].is_empty())
+                .map(|(i, &gain)| (i, gain))
+                .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+            self.stale[p] = false;
+        }
+        self.best[p]
+    }
+
+    fn place(&mut self, p: usize, instrument: i64) {
+        let point = self.points[p];
+        self.spots[p] = Spot::Placed(instrument);
+
+        for (c, other) in self.points.iter().enumerate() {
+            if self.spots[c] == Spot::Free && distance(&point, other) < MUSICIAN_SPACING {
+                self.spots[c] = Spot::Taken;
+            }
+        }
+
+        // Anything placed later in the way of this musician costs their sound
+        for c in 0..self.points.len() {
+            if self.spots[c] != Spot::Free {
+                continue;
+            }
+            let other = self.points[c];
+            let blocked = &self.blocked[p];
+            let attendees = &self.attendees;
+            let problem = self.problem;
+            let mut loss = 0.0;
+            in_window(
+                &self.around[p],
+                angle(&point, &other),
+                window_width(MUSICIAN_RADIUS, distance(&point, &other)),
+                |a| {
+                    if blocked[a] == 0 && is_blocked(&point, &attendees[a], &other, MUSICIAN_RADIUS) {
+                        loss += impact(&problem.attendees[a], instrument, &point);
+                    }
+                },
+            );
+            self.loss[c] += loss;
+        }
+
+        // This musician is now in the way of everyone else
+        for c in 0..self.points.len() {
+            if c == p || self.spots[c] == Spot::Taken {
+                continue;
+            }
+            let mut newly = Vec::new();
+            self.block(c, &point, MUSICIAN_RADIUS, |a| newly.push(a));
+            let other = self.points[c];
+            for a in newly {
+                let attendee = &self.problem.attendees[a];
+                match self.spots[c] {
+                    Spot::Free => {
+                        for (i, &instrument) in self.instruments.iter().enumerate() {
+                            self.gain[c][i] -= impact(attendee, instrument, &other);
+                        }
+                        self.stale[c] = true;
+                    }
+                    Spot::Placed(placed) => {
+                        // That sound is gone already, so nobody else can be
+                        // blamed for blocking it
+                        let lost = impact(attendee, placed, &other);
+                        for (c2, point2) in self.points.iter().enumerate() {
+                            if self.spots[c2] == Spot::Free
+                                && is_blocked(&other, &self.attendees[a], point2, MUSICIAN_RADIUS)
+                            {
+                                self.loss[c2] -= lost;
+                            }
+                        }
+                    }
+                    Spot::Taken => {}
+                }
+            }
+        }
+    }
+}
+
+// Put each of `left` on the first point of a grid at musician spacing that's
+// clear of everyone else. The candidate points can all be used up by musicians
+// packed along the edges, but the grid still has room
+fn seat_on_grid(problem: &Problem, placements: &mut Placements, left: &[usize]) -> bool {
+    let (x_min, y_min, x_max, y_max) = usable_area(problem);
+    let mut seated: Vec<Position> = placements
+        .iter()
+        .enumerate()
+        .filter(|(m, _)| !left.contains(m))
+        .map(|(_, p)| *p)
+        .collect();
+    let mut grid = Vec::new();
+    let mut y = y_min;
+    while y <= y_max {
+        let mut x = x_min;
+        while x <= x_max {
+            grid.push(Position { x, y });
+            x += MUSICIAN_SPACING;
+        }
+        y += MUSICIAN_SPACING;
+    }
+
+    let mut grid = grid.into_iter();
+    for &m in left {
+        let free = grid.find(|point| {
+            seated
+                .iter()
+                .all(|other| distance(point, other) >= MUSICIAN_SPACING)
+        });
+        match free {
+            Some(point) => {
+                placements[m] = point;
+                seated.push(point);
+            }
+            None => return false,
+        }
+    }
+    true
+}
+
+pub fn solve(problem: &Problem) -> Solution {
+    let mut greedy = Greedy::new(problem);
+
+    // Musicians playing the same instrument are interchangeable, so queue
+    // them up by instrument
+    let mut waiting: Vec<Vec<usize>> = vec![Vec::new(); greedy.instruments.len()];
+    for (m, instrument) in problem.musicians.iter().enumerate().rev() {
+        let i = greedy.instruments.binary_search(instrument).unwrap();
+        waiting[i].push(m);
+    }
+
+    let mut placements = vec![Position::default(); problem.musicians.len()];
+    let mut expected = 0.0;
+
+    for n in 0..problem.musicians.len() {
+        let mut choice: Option<(usize, usize, f32)> = None;
+        for p in 0..greedy.points.len() {
+            if greedy.spots[p] != Spot::Free {
+                continue;
+            }
+            if let Some((i, gain)) = greedy.best_at(p, &waiting) {
+                let value = gain - greedy.loss[p];
+                let better = match choice {
+                    Some((_, _, best)) => value > best,
+                    None => true,
+                };
+                if better {
+                    choice = Some((p, i, value));
+                }
+            }
+        }
+        let Some((p, i, value)) = choice else {
+            let left: Vec<usize> = waiting.iter().flatten().copied().collect();
+            eprintln!(
+                "Ran out of candidate points with {} musicians left, seating them on a grid",
+                left.len()
+            );
+            if !seat_on_grid(problem, &mut placements, &left) {
+                eprintln!("No room on the grid either, falling back to trivial");
+                return crate::solver::trivial::solve(problem);
+            }
+            break;
+        };
+
+        let m = waiting[i].pop().unwrap();
+        placements[m] = greedy.points[p];
+        expected += value;
+        greedy.place(p, greedy.instruments[i]);
+
+        if waiting[i].is_empty() {
+            for q in 0..greedy.points.len() {
+                if greedy.best[q].is_some_and(|(best, _)| best == i) {
+                    greedy.stale[q] = true;
+                }
+            }
+        }
+
+        if n % 100 == 0 {
+            eprintln!("Placed {} musicians, expecting {}", n + 1, expected);
+        }
+    }
+
+    let solution = Solution { placements };
+    eprintln!(
+        "Expected {}, scored {}",
+        expected,
+        scorer(problem, &solution)
+    );
+    solution
+}
@@ -1,5 +1,6 @@
 use crate::icfp::*;
 use crate::scorer::*;
+use crate::solver::gravity_of_n::setup_bodies;
 use rapier2d::prelude::*;
 
 use std::fs;
@@ -126,92 +127,18 @@ fn solve_once(problem: &Problem) -> Solution {
     best_solution
 }
 
-fn setup_bodies(
-    solution: &Solution,
-    problem: &Problem,
-) -> (RigidBodySet, ColliderSet, Vec<RigidBodyHandle>) {
-    let mut rigid_body_set = RigidBodySet::new();
-    let mut collider_set = ColliderSet::new();
-
-    // build the walls of the stage
-    let bottom = ColliderBuilder::cuboid(0.1, problem.stage_width).build();
-    let top = ColliderBuilder::cuboid(0.1, problem.stage_height).build();
-    let left = ColliderBuilder::cuboid(problem.stage_height, 0.1).build();
-    let right = ColliderBuilder::cuboid(problem.stage_height, 0.1).build();
-
-    let bottom_left = RigidBodyBuilder::fixed()
-        .translation(vector![
-            problem.stage_bottom_left[0],
-            problem.stage_bottom_left[1]
-        ])
-        .build();
-
-    let bottom_left_handle = rigid_body_set.insert(bottom_left);
-
-    let top_left = RigidBodyBuilder::fixed()
-        .translation(vector![
-            problem.stage_bottom_left[0],
-            problem.stage_bottom_left[1] + problem.stage_height
-        ])
-        .build();
-
-    let top_left_handle = rigid_body_set.insert(top_left);
-
-    let bottom_right = RigidBodyBuilder::fixed()
-        .translation(vector![
-            problem.stage_bottom_left[0] + problem.stage_width,
-            problem.stage_bottom_left[1]
-        ])
-        .build();
-
-    let bottom_right_handle = rigid_body_set.insert(bottom_right);
-
-    // Add the walls to the world
-    collider_set.insert_with_parent(top, top_left_handle, &mut rigid_body_set);
-    collider_set.insert_with_parent(bottom, bottom_left_handle, &mut rigid_body_set);
-    collider_set.insert_with_parent(left, bottom_right_handle, &mut rigid_body_set);
-    collider_set.insert_with_parent(right, bottom_left_handle, &mut rigid_body_set);
-
-    let mut musician_handles: Vec<RigidBodyHandle> = Vec::new();
-    // Loop over musicans and add them to the world
-    for (i, player) in solution.placements.iter().enumerate() {
-        let handle = crate::scorer::add_musician_to_physics(
-            &mut rigid_body_set,
-            &mut collider_set,
-            player.x,
-            player.y,
-            5.0,
-            i as u128,
-        );
-        musician_handles.push(handle);
-    }
-
-    // Loop over pillars and add them to the world
-    for pillar in &problem.pillars {
-        crate::scorer::add_musician_to_physics(
-            &mut rigid_body_set,
-            &mut collider_set,
-            pillar.center[0],
-            pillar.center[1],
-            pillar.radius,
-            u128::MAX,
-        );
-    }
-
-    (rigid_body_set, collider_set, musician_handles)
-}
-
 pub fn solve(problem: &Problem) -> Solution {
     let n = 1;
-    let mut best_score = 0.0;
+    let mut best_score = f32::NEG_INFINITY;
     let mut best_solution = Solution { placements: vec![] };
     for attempt in 0..n {
         eprint!("Attempt {}... ", attempt);
         let solution = solve_once(problem);
         let score = scorer(&problem, &solution);
-        eprintln!("score {}", score);
+        eprintln!("scored {}", score);
         if score > best_score {
             eprintln!("  NEW WINNER");
+            report(score as f64);
             best_score = score;
             best_solution = solution;
         }
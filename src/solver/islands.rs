@@ -0,0 +1,243 @@
+use crate::icfp::*;
+use crate::params::*;
+use crate::scorer::*;
+use crate::solver::ga::*;
+use rand::Rng;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread;
+
+// Which islands send their best to which
+#[derive(Debug, Clone, Copy)]
+pub enum Topology {
+    // Each island to the next, and the last back to the first
+    Ring,
+    // Every island to every other
+    Complete,
+    // Each island to one other, picked afresh at every migration
+    Random,
+}
+
+// Everything but the number of islands, which is up to the machine
+pub const PARAMS: &[Param] = &[
+    Param {
+        name: "population",
+        min: 10.0,
+        max: 500.0,
+        default: 100.0,
+        integer: true,
+    },
+    // A share of the population, like ga
+    Param {
+        name: "survivors",
+        min: 0.02,
+        max: 0.5,
+        default: 0.2,
+        integer: false,
+    },
+    Param {
+        name: "generations",
+        min: 10.0,
+        max: 1000.0,
+        default: 100.0,
+        integer: true,
+    },
+    // 0 for a ring, 1 for complete, 2 for random
+    Param {
+        name: "topology",
+        min: 0.0,
+        max: 2.0,
+        default: 0.0,
+        integer: true,
+    },
+    Param {
+        name: "migration_interval",
+        min: 1.0,
+        max: 50.0,
+        default: 10.0,
+        integer: true,
+    },
+    Param {
+        name: "migrants",
+        min: 1.0,
+        max: 20.0,
+        default: 5.0,
+        integer: true,
+    },
+];
+
+#[derive(Debug, Clone)]
+pub struct Settings {
+    // Defaults to one island per core
+    pub islands: usize,
+    // Per island
+    pub population: usize,
+    pub survivors: usize,
+    pub generations: usize,
+    pub topology: Topology,
+    // Migrate every this many generations...
+    pub migration_interval: usize,
+    // ...sending this many of the island's best
+    pub migrants: usize,
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings::from_params(&Params::defaults(PARAMS))
+    }
+}
+
+impl Settings {
+    pub fn from_params(params: &Params) -> Settings {
+        Settings {
+            islands: thread::available_parallelism().map_or(4, |n| n.get()),
+            population: params.count("population").max(1),
+            survivors: survivor_count(params),
+            generations: params.count("generations"),
+            topology: match params.count("topology") {
+                0 => Topology::Ring,
+                1 => Topology::Complete,
+                _ => Topology::Random,
+            },
+            migration_interval: params.count("migration_interval").max(1),
+            migrants: params.count("migrants"),
+        }
+    }
+}
+
+fn destinations(settings: &Settings, island: usize) -> Vec<usize> {
+    let count = settings.islands;
+    match settings.topology {
+        Topology::Ring => vec![(island + 1) % count],
+        Topology::Complete => (0..count).filter(|&other| other != island).collect(),
+        Topology::Random => {
+            // Anyone but ourselves
+            let other = rand::thread_rng().gen_range(0..count - 1);
+            vec![if other >= island { other + 1 } else { other }]
+        }
+    }
+}
+
+// Arrivals replace the worst of a population sorted best first, which stays
+// the same size and sorted
+fn welcome(population: &mut Vec<Individual>, immigrants: Vec<Individual>) {
+    let room = immigrants.len().min(population.len());
+    population.truncate(population.len() - room);
+    population.extend(immigrants.into_iter().take(room));
+    population.sort_by(|b, a| a.score.partial_cmp(&b.score).unwrap());
+}
+
+// One island: the usual GA, except every so often its best few are sent off
+// to other islands, and whoever has arrived from elsewhere replaces its worst
+fn evolve(
+    problem: &Problem,
+    settings: &Settings,
+    island: usize,
+    outbox: &[Sender<Vec<Individual>>],
+    inbox: Receiver<Vec<Individual>>,
+) -> Individual {
+    let mut population: Vec<Individual> = (0..settings.population)
+        .map(|_| generate_random_individual(problem))
+        .collect();
+    population.sort_by(|b, a| a.score.partial_cmp(&b.score).unwrap());
+
+    for generation in 0..settings.generations {
+        let best = population[0].score;
+        next_generation(
+            problem,
+            &mut population,
+            settings.survivors,
+            settings.population,
+        );
+
+        if settings.islands > 1 && (generation + 1) % settings.migration_interval == 0 {
+            let emigrants: Vec<Individual> =
+                population.iter().take(settings.migrants).cloned().collect();
+            for to in destinations(settings, island) {
+                // The other island may already be done, which is fine
+                let _ = outbox[to].send(emigrants.clone());
+            }
+
+            let immigrants: Vec<Individual> = inbox.try_iter().flatten().collect();
+            welcome(&mut population, immigrants);
+        }
+
+        eprintln!(
+            "Island {} gen {}\tBest {}",
+            island, generation, population[0].score
+        );
+        if population[0].score > best {
+            report(population[0].score as f64);
+        }
+    }
+
+    population.swap_remove(0)
+}
+
+// Island-model GA: several populations evolving on their own threads, with
+// their best individuals migrating between them along `topology`
+pub fn solve_with(problem: &Problem, settings: &Settings) -> Solution {
+    let (outbox, inboxes): (Vec<_>, Vec<_>) = (0..settings.islands).map(|_| channel()).unzip();
+
+    let champions: Vec<Individual> = thread::scope(|scope| {
+        let handles: Vec<_> = inboxes
+            .into_iter()
+            .enumerate()
+            .map(|(island, inbox)| {
+                let outbox = outbox.clone();
+                scope.spawn(move || evolve(problem, settings, island, &outbox, inbox))
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("Island thread panicked"))
+            .collect()
+    });
+
+    let best = champions
+        .into_iter()
+        .max_by(|a, b| a.score.partial_cmp(&b.score).unwrap())
+        .expect("Need at least one island");
+    eprintln!("Best of all islands {}", best.score);
+    best.solution
+}
+
+pub fn solve_with_params(problem: &Problem, params: &Params) -> Solution {
+    solve_with(problem, &Settings::from_params(params))
+}
+
+pub fn solve(problem: &Problem) -> Solution {
+    solve_with(problem, &Settings::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn individuals(scores: &[f32]) -> Vec<Individual> {
+        scores
+            .iter()
+            .map(|&score| Individual {
+                solution: Solution { placements: vec![] },
+                score,
+            })
+            .collect()
+    }
+
+    fn scores(population: &[Individual]) -> Vec<f32> {
+        population.iter().map(|i| i.score).collect()
+    }
+
+    #[test]
+    fn every_migrant_arrives() {
+        let mut population = individuals(&[10.0, 8.0, 6.0, 4.0, 2.0]);
+        welcome(&mut population, individuals(&[9.0, 7.0, 1.0]));
+        assert_eq!(scores(&population), vec![10.0, 9.0, 8.0, 7.0, 1.0]);
+    }
+
+    #[test]
+    fn more_migrants_than_room_keeps_the_size() {
+        let mut population = individuals(&[10.0, 8.0]);
+        welcome(&mut population, individuals(&[9.0, 7.0, 5.0]));
+        assert_eq!(scores(&population), vec![9.0, 7.0]);
+    }
+}
@@ -0,0 +1,116 @@
+use crate::candidates::*;
+use crate::icfp::*;
+use crate::scorer::*;
+use crate::solver::assignment;
+
+// How many of the most promising layouts get fully scored
+const TRIED_LAYOUTS: usize = 2;
+
+// Both orientations, shifted along the rows in quarter steps, with the rows
+// starting from either edge
+fn layouts() -> Vec<Lattice> {
+    let mut layouts = Vec::new();
+    for vertical in [false, true] {
+        for quarter in 0..4 {
+            for flip in [false, true] {
+                layouts.push(Lattice {
+                    spacing: MUSICIAN_SPACING,
+                    vertical,
+                    shift: quarter as f32 * MUSICIAN_SPACING / 4.0,
+                    flip,
+                });
+            }
+        }
+    }
+    layouts
+}
+
+// How many points sit right on the edge of where musicians can stand, which
+// is as close to the audience as they get
+fn edge_points(problem: &Problem, points: &[Position]) -> usize {
+    let (x_min, y_min, x_max, y_max) = usable_area(problem);
+    let on = |a: f32, b: f32| (a - b).abs() < 0.001;
+    points
+        .iter()
+        .filter(|p| on(p.x, x_min) || on(p.x, x_max) || on(p.y, y_min) || on(p.y, y_max))
+        .count()
+}
+
+// Choose which lattice points to fill and with what, best (point, instrument)
+// pairs first. Musicians blocking each other is left to the assignment pass
+fn select(problem: &Problem, points: &[Position]) -> Solution {
+    let hears = lines_of_sight(problem, points, &[]);
+
+    let mut instruments = problem.musicians.clone();
+    instruments.sort();
+    instruments.dedup();
+    let mut waiting: Vec<Vec<usize>> = vec![Vec::new(); instruments.len()];
+    for (m, instrument) in problem.musicians.iter().enumerate() {
+        waiting[instruments.binary_search(instrument).unwrap()].push(m);
+    }
+
+    let mut pairs: Vec<(f32, usize, usize)> = Vec::new();
+    for (p, point) in points.iter().enumerate() {
+        for (i, &instrument) in instruments.iter().enumerate() {
+            let value = problem
+                .attendees
+                .iter()
+                .zip(&hears[p])
+                .filter(|(_, &hears)| hears)
+                .map(|(attendee, _)| impact(attendee, instrument, point))
+                .sum();
+            pairs.push((value, p, i));
+        }
+    }
+    pairs.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap());
+
+    let mut placements = vec![Position::default(); problem.musicians.len()];
+    let mut used = vec![false; points.len()];
+    for (_, p, i) in pairs {
+        if used[p] {
+            continue;
+        }
+        if let Some(m) = waiting[i].pop() {
+            placements[m] = points[p];
+            used[p] = true;
+        }
+    }
+
+    Solution { placements }
+}
+
+pub fn solve(problem: &Problem) -> Solution {
+    let mut layouts: Vec<(usize, Vec<Position>)> = layouts()
+        .iter()
+        .map(|layout| layout.points(problem))
+        .filter(|points| points.len() >= problem.musicians.len())
+        .map(|points| (edge_points(problem, &points), points))
+        .collect();
+    if layouts.is_empty() {
+        eprintln!("No lattice layout fits everyone, falling back to trivial");
+        return crate::solver::trivial::solve(problem);
+    }
+    layouts.sort_by_key(|(edges, _)| std::cmp::Reverse(*edges));
+
+    let mut best_score = f32::NEG_INFINITY;
+    let mut best_solution = Solution { placements: vec![] };
+    for (edges, points) in layouts.iter().take(TRIED_LAYOUTS) {
+        eprintln!(
+            "Trying layout with {} points, {} on the edge",
+            points.len(),
+            edges
+        );
+        let solution = select(problem, points);
+        let solution = assignment::improve(problem, &solution);
+        let score = scorer(problem, &solution);
+        eprintln!("Layout scored {}", score);
+        if score > best_score {
+            eprintln!("  NEW WINNER");
+            report(score as f64);
+            best_score = score;
+            best_solution = solution;
+        }
+    }
+    eprintln!("best score {}", best_score);
+    best_solution
+}
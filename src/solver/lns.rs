@@ -0,0 +1,325 @@
+use crate::candidates::*;
+use crate::deadline;
+use crate::delta::*;
+use crate::icfp::*;
+use crate::params::*;
+use crate::scorer::*;
+use crate::solver::assignment::hungarian;
+use rand::rngs::ThreadRng;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::time::{Duration, Instant};
+
+// How many of those points to try for each musician when rebuilding
+const SAMPLED_POINTS: usize = 200;
+// Most musicians torn out at once
+const MAX_REMOVED: usize = 20;
+const TIME_BUDGET: Duration = Duration::from_secs(60);
+
+pub const PARAMS: &[Param] = &[
+    // 0 for improving only, 1 for record-to-record, 2 for annealing
+    Param {
+        name: "acceptance",
+        min: 0.0,
+        max: 2.0,
+        default: 2.0,
+        integer: true,
+    },
+    // For record-to-record
+    Param {
+        name: "threshold",
+        min: 0.0001,
+        max: 0.05,
+        default: 0.005,
+        integer: false,
+    },
+    // For annealing
+    Param {
+        name: "temperature_start",
+        min: 0.001,
+        max: 0.1,
+        default: 0.01,
+        integer: false,
+    },
+    Param {
+        name: "temperature_end",
+        min: 0.00001,
+        max: 0.001,
+        default: 0.0001,
+        integer: false,
+    },
+    Param {
+        name: "max_removed",
+        min: 2.0,
+        max: 100.0,
+        default: MAX_REMOVED as f64,
+        integer: true,
+    },
+];
+
+// Which rebuilt stages to carry on from
+#[derive(Debug, Clone, Copy)]
+pub enum Acceptance {
+    // Only rebuilds that don't lose score
+    Improving,
+    // Anything within `threshold` (a fraction of the best score) of the best
+    RecordToRecord { threshold: f64 },
+    // Worse rebuilds too, with a chance that shrinks as the temperature drops
+    // from `start` to `end` over the run. Temperatures are a fraction of the
+    // best score
+    Annealing { start: f64, end: f64 },
+}
+
+impl Acceptance {
+    pub fn from_params(params: &Params) -> Acceptance {
+        match params.count("acceptance") {
+            0 => Acceptance::Improving,
+            1 => Acceptance::RecordToRecord {
+                threshold: params.get("threshold"),
+            },
+            _ => Acceptance::Annealing {
+                start: params.get("temperature_start"),
+                end: params.get("temperature_end"),
+            },
+        }
+    }
+
+    fn accepts(
+        &self,
+        current: f64,
+        rebuilt: f64,
+        best: f64,
+        progress: f64,
+        rng: &mut ThreadRng,
+    ) -> bool {
+        let scale = best.abs().max(1.0);
+        match *self {
+            Acceptance::Improving => rebuilt >= current,
+            Acceptance::RecordToRecord { threshold } => rebuilt >= best - threshold * scale,
+            Acceptance::Annealing { start, end } => {
+                let temperature = scale * start * (end / start).powf(progress);
+                rebuilt >= current || rng.gen::<f64>() < ((rebuilt - current) / temperature).exp()
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Settings {
+    pub acceptance: Acceptance,
+    // Most musicians torn out at once
+    pub max_removed: usize,
+    pub time_budget: Duration,
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings::from_params(&Params::defaults(PARAMS))
+    }
+}
+
+impl Settings {
+    pub fn from_params(params: &Params) -> Settings {
+        Settings {
+            acceptance: Acceptance::from_params(params),
+            max_removed: params.count("max_removed").max(1),
+            time_budget: TIME_BUDGET,
+        }
+    }
+}
+
+// Somewhere far outside the room, where a torn out musician blocks nobody
+// while they wait to be put back. Impact rounds up, so they still add 1 for
+// every attendee who likes their instrument, but that goes again as soon as
+// they're put back, so rebuilds are still compared fairly
+fn parking_spot(m: usize) -> Position {
+    Position {
+        x: -1.0e6,
+        y: -1.0e6 - 100.0 * m as f32,
+    }
+}
+
+// Everyone in a random patch of the stage
+fn destroy_region(problem: &Problem, delta: &DeltaScorer, rng: &mut ThreadRng) -> Vec<usize> {
+    let (x_min, y_min, x_max, y_max) = usable_area(problem);
+    let width = (x_max - x_min) * rng.gen_range(0.1..0.3);
+    let height = (y_max - y_min) * rng.gen_range(0.1..0.3);
+    let left = rng.gen_range(x_min..=x_max - width);
+    let bottom = rng.gen_range(y_min..=y_max - height);
+    delta
+        .placements()
+        .iter()
+        .enumerate()
+        .filter(|(_, p)| {
+            p.x >= left && p.x <= left + width && p.y >= bottom && p.y <= bottom + height
+        })
+        .map(|(m, _)| m)
+        .collect()
+}
+
+// Everyone playing some random instrument
+fn destroy_instrument(problem: &Problem, rng: &mut ThreadRng) -> Vec<usize> {
+    let instrument = *problem.musicians.choose(rng).unwrap();
+    (0..problem.musicians.len())
+        .filter(|&m| problem.musicians[m] == instrument)
+        .collect()
+}
+
+// Put each torn out musician back at the best of a sample of candidate
+// points. Gives up if someone has nowhere legal to go
+fn repair_greedy(
+    delta: &mut DeltaScorer,
+    removed: &[usize],
+    points: &[Position],
+    rng: &mut ThreadRng,
+) -> bool {
+    for &m in removed {
+        let mut best: Option<(Position, f64)> = None;
+        for point in points.choose_multiple(rng, SAMPLED_POINTS) {
+            if !delta.can_move(m, point) {
+                continue;
+            }
+            let change = delta.move_delta(m, point);
+            let better = match best {
+                Some((_, best)) => change > best,
+                None => true,
+            };
+            if better {
+                best = Some((*point, change));
+            }
+        }
+        match best {
+            Some((point, _)) => delta.move_to(m, &point),
+            None => return false,
+        }
+    }
+    true
+}
+
+// Put the torn out musicians back where they were before. What's blocked
+// only depends on where everyone ends up, so the order doesn't matter
+fn restore(delta: &mut DeltaScorer, removed: &[usize], before: &[Position]) {
+    for (&m, at) in removed.iter().zip(before) {
+        delta.move_to(m, at);
+    }
+}
+
+// Shuffle the torn out musicians between the spots they were put back in,
+// so each plays where their instrument is worth most
+fn repair_assignment(delta: &mut DeltaScorer, problem: &Problem, removed: &[usize]) {
+    let cost: Vec<Vec<f64>> = removed
+        .iter()
+        .map(|&m| {
+            removed
+                .iter()
+                .map(|&spot| -delta.value_as(spot, problem.musicians[m]))
+                .collect()
+        })
+        .collect();
+    let target = hungarian(&cost);
+
+    // Musician i starts on spot i; trade places until everyone is on target
+    let mut holds: Vec<usize> = (0..removed.len()).collect();
+    let mut holder: Vec<usize> = (0..removed.len()).collect();
+    for i in 0..removed.len() {
+        if holds[i] == target[i] {
+            continue;
+        }
+        let k = holder[target[i]];
+        delta.swap(removed[i], removed[k]);
+        holds[k] = holds[i];
+        holder[holds[i]] = k;
+        holds[i] = target[i];
+        holder[target[i]] = i;
+    }
+}
+
+// Large neighborhood search: tear out a patch of the stage or a whole
+// instrument section, put them back greedily (sometimes re-matching who plays
+// where afterwards), and keep the result if the acceptance rule likes it
+pub fn improve_with(problem: &Problem, solution: &Solution, settings: &Settings) -> Solution {
+    if problem.musicians.is_empty() {
+        return solution.clone();
+    }
+
+    let mut rng = rand::thread_rng();
+    let points = candidates(problem, MAX_CANDIDATES);
+    let mut delta = DeltaScorer::new(problem, solution);
+    let mut best_score = delta.score();
+    let mut best_placements = delta.placements().clone();
+    eprintln!("LNS starting from {}", best_score);
+
+    let time_budget = deadline::budget(settings.time_budget);
+    let start = Instant::now();
+    let mut iteration = 0;
+    let mut accepted = 0;
+    while start.elapsed() < time_budget {
+        iteration += 1;
+
+        let mut removed = if rng.gen_bool(0.5) {
+            destroy_region(problem, &delta, &mut rng)
+        } else {
+            destroy_instrument(problem, &mut rng)
+        };
+        if removed.is_empty() {
+            continue;
+        }
+        removed.shuffle(&mut rng);
+        removed.truncate(settings.max_removed);
+
+        let current = delta.score();
+        let before: Vec<Position> = removed.iter().map(|&m| delta.placements()[m]).collect();
+        for &m in &removed {
+            delta.move_to(m, &parking_spot(m));
+        }
+        if !repair_greedy(&mut delta, &removed, &points, &mut rng) {
+            restore(&mut delta, &removed, &before);
+            continue;
+        }
+        if rng.gen_bool(0.5) {
+            repair_assignment(&mut delta, problem, &removed);
+        }
+
+        let progress = start.elapsed().as_secs_f64() / time_budget.as_secs_f64();
+        if !settings
+            .acceptance
+            .accepts(current, delta.score(), best_score, progress, &mut rng)
+        {
+            restore(&mut delta, &removed, &before);
+            continue;
+        }
+        accepted += 1;
+
+        if delta.score() > best_score {
+            best_score = delta.score();
+            best_placements = delta.placements().clone();
+            eprintln!("Iteration {}: new best {}", iteration, best_score);
+            report(best_score);
+        }
+    }
+    eprintln!("LNS ran {} iterations, accepted {}", iteration, accepted);
+
+    let solution = Solution {
+        placements: best_placements,
+    };
+    eprintln!("LNS scored {}", scorer(problem, &solution));
+    solution
+}
+
+pub fn improve(problem: &Problem, solution: &Solution) -> Solution {
+    improve_with(problem, solution, &Settings::default())
+}
+
+pub fn improve_with_params(problem: &Problem, solution: &Solution, params: &Params) -> Solution {
+    improve_with(problem, solution, &Settings::from_params(params))
+}
+
+pub fn solve_with_params(problem: &Problem, params: &Params) -> Solution {
+    let start = crate::solver::greedy::solve(problem);
+    improve_with_params(problem, &start, params)
+}
+
+pub fn solve(problem: &Problem) -> Solution {
+    let start = crate::solver::greedy::solve(problem);
+    improve(problem, &start)
+}
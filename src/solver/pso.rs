@@ -0,0 +1,194 @@
+use crate::candidates::*;
+use crate::deadline;
+use crate::delta::*;
+use crate::icfp::*;
+use crate::params::*;
+use crate::repair::*;
+use crate::scorer::*;
+use rand::rngs::ThreadRng;
+use rand::Rng;
+use std::time::{Duration, Instant};
+
+const TIME_BUDGET: Duration = Duration::from_secs(60);
+
+pub const PARAMS: &[Param] = &[
+    Param {
+        name: "swarm_size",
+        min: 5.0,
+        max: 100.0,
+        default: 20.0,
+        integer: true,
+    },
+    Param {
+        name: "inertia",
+        min: 0.3,
+        max: 0.95,
+        default: 0.7,
+        integer: false,
+    },
+    Param {
+        name: "cognitive",
+        min: 0.5,
+        max: 2.5,
+        default: 1.5,
+        integer: false,
+    },
+    Param {
+        name: "social",
+        min: 0.5,
+        max: 2.5,
+        default: 1.5,
+        integer: false,
+    },
+    Param {
+        name: "max_velocity",
+        min: 0.01,
+        max: 0.5,
+        default: 0.1,
+        integer: false,
+    },
+];
+
+#[derive(Debug, Clone)]
+pub struct Settings {
+    pub swarm_size: usize,
+    // How much of its velocity a particle keeps from one step to the next
+    pub inertia: f32,
+    // Pull towards the particle's own best, and towards the swarm's best
+    pub cognitive: f32,
+    pub social: f32,
+    // Fastest a musician can move in one step, as a fraction of the stage's
+    // larger side
+    pub max_velocity: f32,
+    pub time_budget: Duration,
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings::from_params(&Params::defaults(PARAMS))
+    }
+}
+
+impl Settings {
+    pub fn from_params(params: &Params) -> Settings {
+        Settings {
+            swarm_size: params.count("swarm_size").max(1),
+            inertia: params.get("inertia") as f32,
+            cognitive: params.get("cognitive") as f32,
+            social: params.get("social") as f32,
+            max_velocity: params.get("max_velocity") as f32,
+            time_budget: TIME_BUDGET,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Particle {
+    placements: Placements,
+    velocity: Vec<(f32, f32)>,
+    best: Placements,
+    best_score: f64,
+}
+
+fn evaluate(problem: &Problem, placements: &Placements) -> f64 {
+    let solution = Solution {
+        placements: placements.clone(),
+    };
+    DeltaScorer::new(problem, &solution).score()
+}
+
+// Particle swarm optimization: every particle is a full set of placements,
+// flying towards the best it has seen and the best the swarm has seen, and
+// repaired back into a legal placement after every step
+pub fn solve_with(problem: &Problem, settings: &Settings) -> Solution {
+    let mut rng = rand::thread_rng();
+    let points = candidates(problem, MAX_CANDIDATES);
+    let max_velocity = settings.max_velocity * problem.stage_width.max(problem.stage_height);
+
+    let mut swarm: Vec<Particle> = (0..settings.swarm_size)
+        .map(|_| {
+            let placements = crate::solver::random::solve(problem).placements;
+            let velocity = placements
+                .iter()
+                .map(|_| {
+                    (
+                        rng.gen_range(-max_velocity..=max_velocity),
+                        rng.gen_range(-max_velocity..=max_velocity),
+                    )
+                })
+                .collect();
+            let best_score = evaluate(problem, &placements);
+            Particle {
+                best: placements.clone(),
+                placements,
+                velocity,
+                best_score,
+            }
+        })
+        .collect();
+
+    let leader = swarm
+        .iter()
+        .max_by(|a, b| a.best_score.total_cmp(&b.best_score))
+        .expect("Swarm needs at least one particle");
+    let mut best_placements = leader.best.clone();
+    let mut best_score = leader.best_score;
+    eprintln!("PSO starting from {}", best_score);
+
+    let time_budget = deadline::budget(settings.time_budget);
+    let start = Instant::now();
+    let mut step = 0;
+    while start.elapsed() < time_budget {
+        step += 1;
+        for particle in swarm.iter_mut() {
+            for (m, leader) in best_placements.iter().enumerate() {
+                let at = particle.placements[m];
+                let own = particle.best[m];
+                let (vx, vy) = particle.velocity[m];
+                let pull = |velocity: f32, at: f32, own: f32, leader: f32, rng: &mut ThreadRng| {
+                    let velocity = settings.inertia * velocity
+                        + settings.cognitive * rng.gen::<f32>() * (own - at)
+                        + settings.social * rng.gen::<f32>() * (leader - at);
+                    velocity.clamp(-max_velocity, max_velocity)
+                };
+                let vx = pull(vx, at.x, own.x, leader.x, &mut rng);
+                let vy = pull(vy, at.y, own.y, leader.y, &mut rng);
+                particle.velocity[m] = (vx, vy);
+                particle.placements[m] = Position {
+                    x: at.x + vx,
+                    y: at.y + vy,
+                };
+            }
+            if repair_placements(problem, &mut particle.placements, &points).is_err() {
+                continue;
+            }
+
+            let score = evaluate(problem, &particle.placements);
+            if score > particle.best_score {
+                particle.best_score = score;
+                particle.best = particle.placements.clone();
+            }
+            if score > best_score {
+                best_score = score;
+                best_placements = particle.placements.clone();
+                eprintln!("Step {}: new best {}", step, best_score);
+                report(best_score);
+            }
+        }
+    }
+    eprintln!("PSO ran {} steps", step);
+
+    let solution = Solution {
+        placements: best_placements,
+    };
+    eprintln!("PSO scored {}", scorer(problem, &solution));
+    solution
+}
+
+pub fn solve_with_params(problem: &Problem, params: &Params) -> Solution {
+    solve_with(problem, &Settings::from_params(params))
+}
+
+pub fn solve(problem: &Problem) -> Solution {
+    solve_with(problem, &Settings::default())
+}
@@ -1,8 +1,18 @@
 use crate::icfp::*;
+use crate::params::*;
 use crate::scorer::*;
+use crate::symmetry::*;
 use rand::Rng;
 use std::collections::HashSet;
 
+pub const PARAMS: &[Param] = &[Param {
+    name: "attempts",
+    min: 1.0,
+    max: 5000.0,
+    default: 1000.0,
+    integer: true,
+}];
+
 fn solve_once(problem: &Problem) -> Solution {
     let mut rng = rand::thread_rng();
     let mut players: Vec<Position> = Vec::new();
@@ -38,17 +48,26 @@ fn solve_once(problem: &Problem) -> Solution {
     }
 }
 
-pub fn solve(problem: &Problem) -> Solution {
-    let n = 1000;
-    let mut best_score = 0.0;
+pub fn solve_with_params(problem: &Problem, params: &Params) -> Solution {
+    let n = params.count("attempts");
+    let mut best_score = f32::NEG_INFINITY;
     let mut best_solution = Solution { placements: vec![] };
+    let groups = groups(problem);
+    let mut tried: HashSet<Key> = HashSet::new();
     for attempt in 0..n {
         eprint!("Attempt {}... ", attempt);
         let solution = solve_once(&problem);
+        // Same spots for each instrument as before, just with the musicians
+        // shuffled around, so it would score the same
+        if !tried.insert(key(&groups, &solution)) {
+            eprintln!("already tried");
+            continue;
+        }
         let score = scorer(&problem, &solution);
-        eprintln!("score {}", score);
+        eprintln!("scored {}", score);
         if score > best_score {
             eprintln!("  NEW WINNER");
+            report(score as f64);
             best_score = score;
             best_solution = solution;
         }
@@ -56,3 +75,7 @@ pub fn solve(problem: &Problem) -> Solution {
     eprintln!("best score {}", best_score);
     best_solution
 }
+
+pub fn solve(problem: &Problem) -> Solution {
+    solve_with_params(problem, &Params::defaults(PARAMS))
+}
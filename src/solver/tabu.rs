@@ -0,0 +1,184 @@
+use crate::candidates::*;
+use crate::deadline;
+use crate::delta::*;
+use crate::geometry::*;
+use crate::icfp::*;
+use crate::params::*;
+use crate::scorer::*;
+use crate::solver::ga::pick_swap;
+use rand::Rng;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+const TIME_BUDGET: Duration = Duration::from_secs(60);
+
+pub const PARAMS: &[Param] = &[
+    Param {
+        name: "relocations",
+        min: 5.0,
+        max: 200.0,
+        default: 30.0,
+        integer: true,
+    },
+    Param {
+        name: "swaps",
+        min: 0.0,
+        max: 200.0,
+        default: 30.0,
+        integer: true,
+    },
+    Param {
+        name: "tenure",
+        min: 1.0,
+        max: 200.0,
+        default: 20.0,
+        integer: true,
+    },
+];
+
+#[derive(Debug, Clone)]
+pub struct Settings {
+    // How many moves of each kind to sample every iteration
+    pub relocations: usize,
+    pub swaps: usize,
+    // How many iterations a moved musician, and the spot it left, stay tabu
+    pub tenure: usize,
+    pub time_budget: Duration,
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings::from_params(&Params::defaults(PARAMS))
+    }
+}
+
+impl Settings {
+    pub fn from_params(params: &Params) -> Settings {
+        Settings {
+            relocations: params.count("relocations"),
+            swaps: params.count("swaps"),
+            tenure: params.count("tenure"),
+            time_budget: TIME_BUDGET,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Move {
+    Relocate(usize, Position),
+    Swap(usize, usize),
+}
+
+// Tabu search over relocating a musician to a candidate point and swapping
+// two musicians. Each step takes the best sampled move even if it makes
+// things worse, but won't touch recently moved musicians or move anyone back
+// into a recently vacated spot, unless that would beat the best score so far
+pub fn improve_with(problem: &Problem, solution: &Solution, settings: &Settings) -> Solution {
+    let musician_count = problem.musicians.len();
+    if musician_count == 0 {
+        return solution.clone();
+    }
+
+    let mut rng = rand::thread_rng();
+    let points = candidates(problem, MAX_CANDIDATES);
+    let mut delta = DeltaScorer::new(problem, solution);
+    let mut best_score = delta.score();
+    let mut best_placements = delta.placements().clone();
+    eprintln!("Tabu starting from {}", best_score);
+
+    let mut tabu_until = vec![0; musician_count];
+    let mut vacated: VecDeque<(Position, usize)> = VecDeque::new();
+
+    let time_budget = deadline::budget(settings.time_budget);
+    let start = Instant::now();
+    let mut iteration = 0;
+    while start.elapsed() < time_budget {
+        iteration += 1;
+        while vacated.front().is_some_and(|(_, until)| *until <= iteration) {
+            vacated.pop_front();
+        }
+
+        let mut chosen: Option<(Move, f64)> = None;
+        let mut consider = |candidate: Move, change: f64, tabu: bool| {
+            let aspiration = delta.score() + change > best_score;
+            if tabu && !aspiration {
+                return;
+            }
+            let better = match chosen {
+                Some((_, best)) => change > best,
+                None => true,
+            };
+            if better {
+                chosen = Some((candidate, change));
+            }
+        };
+
+        for _ in 0..settings.relocations {
+            let m = rng.gen_range(0..musician_count);
+            let to = points[rng.gen_range(0..points.len())];
+            if !delta.can_move(m, &to) {
+                continue;
+            }
+            let tabu = tabu_until[m] > iteration
+                || vacated
+                    .iter()
+                    .any(|(spot, _)| distance(spot, &to) < MUSICIAN_SPACING / 2.0);
+            consider(Move::Relocate(m, to), delta.move_delta(m, &to), tabu);
+        }
+
+        for _ in 0..settings.swaps {
+            let (a, b) = pick_swap(delta.placements());
+            if problem.musicians[a] == problem.musicians[b] {
+                continue;
+            }
+            let tabu = tabu_until[a] > iteration || tabu_until[b] > iteration;
+            consider(Move::Swap(a, b), delta.swap_delta(a, b), tabu);
+        }
+
+        match chosen {
+            Some((Move::Relocate(m, to), _)) => {
+                vacated.push_back((delta.placements()[m], iteration + settings.tenure));
+                tabu_until[m] = iteration + settings.tenure;
+                delta.move_to(m, &to);
+            }
+            Some((Move::Swap(a, b), _)) => {
+                tabu_until[a] = iteration + settings.tenure;
+                tabu_until[b] = iteration + settings.tenure;
+                delta.swap(a, b);
+            }
+            None => continue,
+        }
+
+        if delta.score() > best_score {
+            best_score = delta.score();
+            best_placements = delta.placements().clone();
+            eprintln!("Iteration {}: new best {}", iteration, best_score);
+            report(best_score);
+        }
+    }
+    eprintln!("Tabu ran {} iterations", iteration);
+
+    let solution = Solution {
+        placements: best_placements,
+    };
+    eprintln!("Tabu scored {}", scorer(problem, &solution));
+    solution
+}
+
+pub fn improve(problem: &Problem, solution: &Solution) -> Solution {
+    improve_with(problem, solution, &Settings::default())
+}
+
+pub fn improve_with_params(problem: &Problem, solution: &Solution, params: &Params) -> Solution {
+    improve_with(problem, solution, &Settings::from_params(params))
+}
+
+pub fn solve_with_params(problem: &Problem, params: &Params) -> Solution {
+    let start = crate::solver::greedy::solve(problem);
+    improve_with_params(problem, &start, params)
+}
+
+pub fn solve(problem: &Problem) -> Solution {
+    let start = crate::solver::greedy::solve(problem);
+    improve(problem, &start)
+}
@@ -0,0 +1,164 @@
+use crate::candidates::MUSICIAN_RADIUS;
+use crate::geometry::*;
+use crate::icfp::*;
+
+// Width of the soft edge around every blocker. A line of sight right on a
+// blocker's edge counts as half blocked, and one `SOFTNESS` further out as
+// about a quarter blocked
+const SOFTNESS: f64 = 1.0;
+// Blockers more than this many softness widths clear of a line of sight are
+// left out, as they'd hardly change anything
+const CUTOFF: f64 = 6.0;
+
+// A smoothed version of the score, where blocking fades in over `SOFTNESS`
+// instead of being all or nothing and impact isn't rounded, so it can be
+// differentiated with respect to where every musician stands
+pub struct SmoothScore {
+    pub total: f64,
+    // What each musician adds
+    pub values: Vec<f64>,
+    // Gradient of the total with respect to each musician's position
+    pub gradient: Vec<(f64, f64)>,
+}
+
+// A blocker close enough to a line of sight to matter, with how much that
+// line's clearness changes as the musician or the blocker moves
+struct Touch {
+    attendee: usize,
+    blocker: Option<usize>,
+    weight: f64,
+    d_from: (f64, f64),
+    d_center: (f64, f64),
+}
+
+// ln(1 / (1 + e^-x)) without overflowing either way
+fn log_sigmoid(x: f64) -> f64 {
+    if x < -30.0 {
+        x
+    } else {
+        -(-x).exp().ln_1p()
+    }
+}
+
+fn sigmoid(x: f64) -> f64 {
+    1.0 / (1.0 + (-x).exp())
+}
+
+// Distance from `center` to the segment from `from` to `to`, with its
+// gradient with respect to `from` and with respect to `center`
+fn segment_distance_gradient(
+    from: &Position,
+    to: &Position,
+    center: &Position,
+) -> (f64, (f64, f64), (f64, f64)) {
+    let (fx, fy) = (from.x as f64, from.y as f64);
+    let (dx, dy) = (to.x as f64 - fx, to.y as f64 - fy);
+    let (cx, cy) = (center.x as f64, center.y as f64);
+    let length_squared = dx * dx + dy * dy;
+    let t = if length_squared == 0.0 {
+        0.0
+    } else {
+        (((cx - fx) * dx + (cy - fy) * dy) / length_squared).clamp(0.0, 1.0)
+    };
+    // The closest point on the segment only moves with `from` in proportion
+    // to how near it is to that end
+    let (qx, qy) = (fx + t * dx, fy + t * dy);
+    let distance = ((cx - qx).powi(2) + (cy - qy).powi(2)).sqrt();
+    if distance == 0.0 {
+        return (0.0, (0.0, 0.0), (0.0, 0.0));
+    }
+    let (ux, uy) = ((cx - qx) / distance, (cy - qy) / distance);
+    (distance, (-(1.0 - t) * ux, -(1.0 - t) * uy), (ux, uy))
+}
+
+pub fn smooth_score(problem: &Problem, placements: &Placements) -> SmoothScore {
+    let attendees: Vec<Position> = problem
+        .attendees
+        .iter()
+        .map(|a| Position { x: a.x, y: a.y })
+        .collect();
+    // Everything that can block: musicians (by index) and pillars
+    let mut blockers: Vec<(Position, f32, Option<usize>)> = placements
+        .iter()
+        .enumerate()
+        .map(|(k, p)| (*p, MUSICIAN_RADIUS, Some(k)))
+        .collect();
+    for pillar in &problem.pillars {
+        let center = Position {
+            x: pillar.center[0],
+            y: pillar.center[1],
+        };
+        blockers.push((center, pillar.radius, None));
+    }
+
+    let mut values = vec![0.0; placements.len()];
+    let mut gradient = vec![(0.0, 0.0); placements.len()];
+    for (m, at) in placements.iter().enumerate() {
+        let around = sorted_by_angle(at, &attendees);
+
+        // How clear each line of sight is, as a log so blockers add up, plus
+        // each blocker's pull on the musician and on itself
+        let mut log_clear = vec![0.0; attendees.len()];
+        let mut touches: Vec<Touch> = Vec::new();
+        for (center, radius, owner) in &blockers {
+            if *owner == Some(m) {
+                continue;
+            }
+            let reach = *radius + (CUTOFF * SOFTNESS) as f32;
+            in_window(
+                &around,
+                angle(at, center),
+                window_width(reach, distance(at, center)),
+                |a| {
+                    let (gap, d_from, d_center) =
+                        segment_distance_gradient(at, &attendees[a], center);
+                    let x = (gap - *radius as f64) / SOFTNESS;
+                    if x > CUTOFF {
+                        return;
+                    }
+                    log_clear[a] += log_sigmoid(x);
+                    // d/dx ln(sigmoid(x)) = sigmoid(-x)
+                    let weight = sigmoid(-x) / SOFTNESS;
+                    touches.push(Touch {
+                        attendee: a,
+                        blocker: *owner,
+                        weight,
+                        d_from,
+                        d_center,
+                    });
+                },
+            );
+        }
+
+        let instrument = problem.musicians[m] as usize;
+        let (px, py) = (at.x as f64, at.y as f64);
+        let mut heard = vec![0.0; attendees.len()];
+        for (a, attendee) in problem.attendees.iter().enumerate() {
+            let (dx, dy) = (attendee.x as f64 - px, attendee.y as f64 - py);
+            let distance_squared = dx * dx + dy * dy;
+            let impact = 1_000_000.0 * attendee.tastes[instrument] as f64 / distance_squared;
+            let clear = log_clear[a].exp();
+            heard[a] = impact * clear;
+            values[m] += heard[a];
+            // d/dp 1/|a - p|^2 = 2 (a - p) / |a - p|^4
+            gradient[m].0 += clear * impact * 2.0 * dx / distance_squared;
+            gradient[m].1 += clear * impact * 2.0 * dy / distance_squared;
+        }
+
+        for touch in touches {
+            let pull = heard[touch.attendee] * touch.weight;
+            gradient[m].0 += pull * touch.d_from.0;
+            gradient[m].1 += pull * touch.d_from.1;
+            if let Some(k) = touch.blocker {
+                gradient[k].0 += pull * touch.d_center.0;
+                gradient[k].1 += pull * touch.d_center.1;
+            }
+        }
+    }
+
+    SmoothScore {
+        total: values.iter().sum(),
+        values,
+        gradient,
+    }
+}
@@ -0,0 +1,119 @@
+use crate::icfp::*;
+use std::collections::BTreeMap;
+
+// Musicians playing the same instrument are interchangeable: swapping two of
+// them changes nothing about the score. So a solution is really a set of
+// positions per instrument, and any two solutions that only differ by such
+// swaps are the same solution
+
+// The musicians playing each instrument, by instrument
+pub fn groups(problem: &Problem) -> Vec<Vec<usize>> {
+    let mut groups: BTreeMap<i64, Vec<usize>> = BTreeMap::new();
+    for (m, &instrument) in problem.musicians.iter().enumerate() {
+        groups.entry(instrument).or_default().push(m);
+    }
+    groups.into_values().collect()
+}
+
+// The same solution with each instrument's positions handed out in sorted
+// order, so that equivalent solutions come out identical
+pub fn canonical(groups: &[Vec<usize>], solution: &Solution) -> Solution {
+    let mut placements = solution.placements.clone();
+    for group in groups {
+        let mut positions: Vec<Position> = group.iter().map(|&m| placements[m]).collect();
+        positions.sort_by(|a, b| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+        for (&m, position) in group.iter().zip(positions) {
+            placements[m] = position;
+        }
+    }
+    Solution { placements }
+}
+
+// Something to hash a solution by that's the same for equivalent solutions
+pub type Key = Vec<(u32, u32)>;
+
+pub fn key(groups: &[Vec<usize>], solution: &Solution) -> Key {
+    canonical(groups, solution)
+        .placements
+        .iter()
+        .map(|p| (p.x.to_bits(), p.y.to_bits()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scorer::*;
+
+    fn problem() -> Problem {
+        Problem {
+            room_width: 100.0,
+            room_height: 100.0,
+            stage_width: 60.0,
+            stage_height: 60.0,
+            stage_bottom_left: vec![20.0, 20.0],
+            musicians: vec![1, 0, 1, 0, 1],
+            attendees: vec![
+                Attendee {
+                    x: 5.0,
+                    y: 50.0,
+                    tastes: vec![1000.0, -500.0],
+                },
+                Attendee {
+                    x: 95.0,
+                    y: 30.0,
+                    tastes: vec![-200.0, 800.0],
+                },
+            ],
+            pillars: vec![],
+        }
+    }
+
+    fn solution() -> Solution {
+        Solution {
+            placements: vec![
+                Position { x: 70.0, y: 30.0 },
+                Position { x: 30.0, y: 50.0 },
+                Position { x: 50.0, y: 70.0 },
+                Position { x: 30.0, y: 30.0 },
+                Position { x: 50.0, y: 45.0 },
+            ],
+        }
+    }
+
+    fn swapped(solution: &Solution, a: usize, b: usize) -> Solution {
+        let mut swapped = solution.clone();
+        swapped.placements.swap(a, b);
+        swapped
+    }
+
+    #[test]
+    fn groups_are_by_instrument() {
+        assert_eq!(groups(&problem()), vec![vec![1, 3], vec![0, 2, 4]]);
+    }
+
+    #[test]
+    fn same_instrument_swaps_share_a_key() {
+        let groups = groups(&problem());
+        let solution = solution();
+        let key = key(&groups, &solution);
+        for (a, b) in [(1, 3), (0, 2), (0, 4), (2, 4)] {
+            assert_eq!(super::key(&groups, &swapped(&solution, a, b)), key);
+        }
+        // Musicians 0 and 1 play different instruments
+        assert_ne!(super::key(&groups, &swapped(&solution, 0, 1)), key);
+    }
+
+    #[test]
+    fn canonical_keeps_score_and_is_stable() {
+        let problem = problem();
+        let groups = groups(&problem);
+        let solution = solution();
+        let canonical = canonical(&groups, &solution);
+        assert_eq!(scorer(&problem, &canonical), scorer(&problem, &solution));
+        assert_eq!(
+            super::canonical(&groups, &canonical).placements,
+            canonical.placements
+        );
+    }
+}